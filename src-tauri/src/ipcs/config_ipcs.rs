@@ -2,9 +2,12 @@ use std::error::Error;
 
 use serde::{Deserialize, Serialize};
 
-use crate::services::{
-    config_service::ConfigService,
-    ipc_service::{IpcReplier, IpcService},
+use crate::{
+    model::gamepad::LedColor,
+    services::{
+        config_service::ConfigService,
+        ipc_service::{IpcReplier, IpcService},
+    },
 };
 
 pub async fn register() {
@@ -54,6 +57,94 @@ pub async fn register() {
         },
     )
     .await;
+
+    ipc.on(
+        "get-profiles",
+        async |_, replier: IpcReplier| -> Result<(), Box<dyn Error>> {
+            let mut watcher = config.watch();
+            let mut profiles = config.get_config().profile_list();
+
+            replier.reply(profiles.clone());
+
+            tokio::select! {
+                _ = async {
+                    while watcher.changed().await.is_ok() {
+                        let updated = watcher.borrow_and_update().profile_list();
+
+                        // Most config changes leave the profiles untouched
+                        if updated != profiles {
+                            replier.reply(updated.clone());
+                            profiles = updated;
+                        }
+                    }
+                } => {},
+                _ = replier.wait_until_closed() => {}
+            };
+
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "create-profile",
+        async |data, replier: IpcReplier| -> Result<(), Box<dyn Error>> {
+            let name: String = serde_json::from_value(data.unwrap())?;
+            replier.reply(config.create_profile(&name)?);
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "clone-profile",
+        async |data, replier: IpcReplier| -> Result<(), Box<dyn Error>> {
+            let data: ProfileRequest = serde_json::from_value(data.unwrap())?;
+            replier.reply(config.clone_profile(&data.id, &data.name)?);
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "rename-profile",
+        async |data, _replier| -> Result<(), Box<dyn Error>> {
+            let data: ProfileRequest = serde_json::from_value(data.unwrap())?;
+            config.rename_profile(&data.id, &data.name)?;
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "set-profile-led",
+        async |data, _replier| -> Result<(), Box<dyn Error>> {
+            let data: SetProfileLedRequest = serde_json::from_value(data.unwrap())?;
+            config.set_profile_led(&data.id, data.color)?;
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "delete-profile",
+        async |data, _replier| -> Result<(), Box<dyn Error>> {
+            let id: String = serde_json::from_value(data.unwrap())?;
+            config.delete_profile(&id)?;
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "activate-profile",
+        async |data, _replier| -> Result<(), Box<dyn Error>> {
+            let id: String = serde_json::from_value(data.unwrap())?;
+            config.activate_profile(&id)?;
+            Ok(())
+        },
+    )
+    .await;
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -61,3 +152,15 @@ struct SetConfigRequest {
     key: String,
     value: serde_json::Value,
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ProfileRequest {
+    id: String,
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SetProfileLedRequest {
+    id: String,
+    color: Option<LedColor>,
+}
@@ -1,11 +1,16 @@
 use crate::{
-    model::gamepad::GamepadState,
+    model::gamepad::{GamepadState, LedColor},
     services::{
+        calibration_wizard,
+        config_service::ConfigService,
         gamepad_service::{self},
         ipc_service::IpcService,
+        mapping_wizard,
     },
 };
+use serde::{Deserialize, Serialize};
 use std::error::Error;
+use tokio::sync::broadcast::error::RecvError;
 
 pub async fn register() {
     let ipc = IpcService::get_instance();
@@ -49,4 +54,169 @@ pub async fn register() {
         },
     )
     .await;
+
+    ipc.on(
+        "input-events",
+        async |_, replier| -> Result<(), Box<dyn Error>> {
+            let mut receiver = {
+                let state = gamepad_service::GAMEPAD_STATE.read().unwrap();
+                state.subscribe_input_events()
+            };
+
+            tokio::select! {
+                _ = async {
+                    loop {
+                        match receiver.recv().await {
+                            Ok(event) => replier.reply(event),
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(e) => {
+                                println!("error: {:?}", e);
+                                break;
+                            }
+                        }
+                    }
+                } => {},
+                _ = replier.wait_until_closed() => {}
+            };
+
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "on-low-battery",
+        async |_, replier| -> Result<(), Box<dyn Error>> {
+            let mut receiver = {
+                let state = gamepad_service::GAMEPAD_STATE.read().unwrap();
+                state.subscribe_low_battery()
+            };
+
+            tokio::select! {
+                _ = async {
+                    loop {
+                        match receiver.recv().await {
+                            Ok(warning) => replier.reply(warning),
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(e) => {
+                                println!("error: {:?}", e);
+                                break;
+                            }
+                        }
+                    }
+                } => {},
+                _ = replier.wait_until_closed() => {}
+            };
+
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "rename-device",
+        async |data, _replier| -> Result<(), Box<dyn Error>> {
+            let data: RenameDeviceRequest = serde_json::from_value(data.unwrap())?;
+            ConfigService::get_instance().rename_device(&data.key, data.alias)?;
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "forget-device",
+        async |data, _replier| -> Result<(), Box<dyn Error>> {
+            let key: String = serde_json::from_value(data.unwrap())?;
+            ConfigService::get_instance().forget_device(&key);
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "set-device-led",
+        async |data, _replier| -> Result<(), Box<dyn Error>> {
+            let data: SetDeviceLedRequest = serde_json::from_value(data.unwrap())?;
+            ConfigService::get_instance().set_device_led(&data.key, data.color)?;
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "import-sdl-mappings",
+        async |data, replier| -> Result<(), Box<dyn Error>> {
+            let path: String = serde_json::from_value(data.unwrap())?;
+            let count = ConfigService::get_instance().import_gamecontrollerdb(&path)?;
+            replier.reply(count);
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "remove-sdl-mapping",
+        async |data, _replier| -> Result<(), Box<dyn Error>> {
+            let guid: String = serde_json::from_value(data.unwrap())?;
+            ConfigService::get_instance().remove_sdl_mapping(&guid);
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "calibrate-controller",
+        async |data, replier| -> Result<(), Box<dyn Error>> {
+            let gamepad_id: u32 = serde_json::from_value(data.unwrap())?;
+
+            tokio::select! {
+                result = calibration_wizard::run_calibration_wizard(gamepad_id, |update| replier.reply(update)) => {
+                    result?;
+                },
+                _ = replier.wait_until_closed() => {}
+            };
+
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "reset-calibration",
+        async |data, _replier| -> Result<(), Box<dyn Error>> {
+            let key: String = serde_json::from_value(data.unwrap())?;
+            ConfigService::get_instance().set_device_calibration(&key, None)?;
+            Ok(())
+        },
+    )
+    .await;
+
+    ipc.on(
+        "map-controller",
+        async |data, replier| -> Result<(), Box<dyn Error>> {
+            let gamepad_id: u32 = serde_json::from_value(data.unwrap())?;
+
+            tokio::select! {
+                result = mapping_wizard::run_mapping_wizard(gamepad_id, |update| replier.reply(update)) => {
+                    result?;
+                },
+                _ = replier.wait_until_closed() => {}
+            };
+
+            Ok(())
+        },
+    )
+    .await;
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RenameDeviceRequest {
+    key: String,
+    alias: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SetDeviceLedRequest {
+    key: String,
+    color: Option<LedColor>,
 }
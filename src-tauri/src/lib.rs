@@ -3,7 +3,7 @@ use std::sync::OnceLock;
 use services::config_service::{CONFIG_SERVICE, ConfigService};
 use tauri::{AppHandle, Manager, menu::Menu, menu::MenuItem, tray::TrayIconBuilder};
 
-use crate::services::{mapping_service, virtual_keyboard};
+use crate::services::{led_service, mapping_service, virtual_keyboard};
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 mod ipcs;
@@ -40,6 +40,12 @@ pub fn run() {
                 mapping_service::start_mapping_system().await.unwrap();
             });
 
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = led_service::start_led_sync().await {
+                    eprintln!("LED sync stopped: {}", e);
+                }
+            });
+
             let open_i = MenuItem::with_id(app, "open", "Open", true, None::<&str>)?;
             let keyboard_i =
                 MenuItem::with_id(app, "keyboard", "Virtual keyboard", true, None::<&str>)?;
@@ -0,0 +1,250 @@
+use std::{collections::HashMap, f32::consts::TAU};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::gamepad::GamepadAxis;
+
+/// Number of directions the reach of a stick is measured in.
+pub const STICK_REACH_SECTORS: usize = 16;
+
+/// Smallest reach used when correcting a stick, keeps a bad recording from amplifying noise.
+const MIN_STICK_REACH: f32 = 0.5;
+
+/// Raw range of one axis, the calibrated value is 0 at `center` and full scale at `min` and `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/calibration.ts")]
+pub struct AxisCalibration {
+    pub center: i16,
+    pub min: i16,
+    pub max: i16,
+}
+
+impl AxisCalibration {
+    pub fn apply(&self, raw: i16) -> i16 {
+        let offset = raw as i32 - self.center as i32;
+
+        let range = if offset >= 0 {
+            self.max as i32 - self.center as i32
+        } else {
+            self.center as i32 - self.min as i32
+        };
+
+        if range <= 0 {
+            return 0;
+        }
+
+        (offset * i16::MAX as i32 / range).clamp(-(i16::MAX as i32), i16::MAX as i32) as i16
+    }
+}
+
+/// Distance reached at full tilt in each direction once the axes are calibrated, 1.0 everywhere is a round stick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/calibration.ts")]
+pub struct StickCalibration {
+    /// One entry per direction starting from the right, `STICK_REACH_SECTORS` entries
+    pub reach: Vec<f32>,
+}
+
+impl StickCalibration {
+    /// Builds the reach from calibrated positions (between -1 and 1) recorded while rolling the stick along its edge.
+    pub fn from_samples(samples: &[(f32, f32)]) -> Self {
+        let mut reach = vec![0.0; STICK_REACH_SECTORS];
+
+        // Positions well inside the edge come from the stick moving between directions
+        for &(x, y) in samples
+            .iter()
+            .filter(|(x, y)| x.hypot(*y) >= MIN_STICK_REACH)
+        {
+            let sector = sector_position(x, y).round() as usize % STICK_REACH_SECTORS;
+            reach[sector] = f32::max(reach[sector], x.hypot(y));
+        }
+
+        // Directions never reached are left uncorrected
+        for value in reach.iter_mut().filter(|value| **value <= 0.0) {
+            *value = 1.0;
+        }
+
+        Self { reach }
+    }
+
+    /// Mean distance between the reach and a perfect circle, 0.0 for a round stick.
+    pub fn circularity_error(&self) -> f32 {
+        if self.reach.is_empty() {
+            return 0.0;
+        }
+
+        self.reach
+            .iter()
+            .map(|reach| (reach - 1.0).abs())
+            .sum::<f32>()
+            / self.reach.len() as f32
+    }
+
+    /// Factor to apply to both axes so that full tilt lands on the unit circle in every direction.
+    pub fn radial_scale(&self, x: f32, y: f32) -> f32 {
+        let radius = x.hypot(y);
+
+        if radius <= f32::EPSILON || self.reach.is_empty() {
+            return 1.0;
+        }
+
+        let reach = self.reach_at(x, y).max(MIN_STICK_REACH);
+
+        (radius / reach).min(1.0) / radius
+    }
+
+    fn reach_at(&self, x: f32, y: f32) -> f32 {
+        let sectors = self.reach.len();
+        let position = sector_position(x, y) * sectors as f32 / STICK_REACH_SECTORS as f32;
+        let index = position.floor() as usize % sectors;
+        let t = position.fract();
+
+        self.reach[index] + (self.reach[(index + 1) % sectors] - self.reach[index]) * t
+    }
+}
+
+/// Angle of the position expressed in sectors, between 0 and `STICK_REACH_SECTORS`.
+fn sector_position(x: f32, y: f32) -> f32 {
+    (y.atan2(x) / TAU).rem_euclid(1.0) * STICK_REACH_SECTORS as f32
+}
+
+/// Calibration of a controller, stored on its known device and applied before any mapping sees the values.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/calibration.ts")]
+pub struct ControllerCalibration {
+    #[ts(type = "Partial<Record<GamepadAxis, AxisCalibration>>")]
+    pub axes: HashMap<GamepadAxis, AxisCalibration>,
+    pub left_stick: Option<StickCalibration>,
+    pub right_stick: Option<StickCalibration>,
+}
+
+impl ControllerCalibration {
+    pub fn axis(&self, axis: &GamepadAxis) -> Option<&AxisCalibration> {
+        self.axes.get(axis)
+    }
+
+    /// Circularity of the stick `axis` belongs to, with the x and y axes of that stick.
+    pub fn stick(
+        &self,
+        axis: &GamepadAxis,
+    ) -> Option<(&StickCalibration, GamepadAxis, GamepadAxis)> {
+        match axis {
+            GamepadAxis::LeftX | GamepadAxis::LeftY => self
+                .left_stick
+                .as_ref()
+                .map(|stick| (stick, GamepadAxis::LeftX, GamepadAxis::LeftY)),
+            GamepadAxis::RightX | GamepadAxis::RightY => self
+                .right_stick
+                .as_ref()
+                .map(|stick| (stick, GamepadAxis::RightX, GamepadAxis::RightY)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/calibration.ts")]
+pub enum CalibrationStep {
+    /// Sticks and triggers left at rest
+    Center,
+    /// Sticks rolled along their edge and triggers pressed all the way
+    Range,
+}
+
+/// Progress of the calibration wizard, streamed to the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/calibration.ts")]
+pub enum CalibrationUpdate {
+    /// The step is recorded for `seconds` from now
+    Prompt { step: CalibrationStep, seconds: u32 },
+    /// The stick errors are the circularity error of each stick before correction, 0.0 for a round stick
+    #[serde(rename_all = "camelCase")]
+    Done {
+        calibration: ControllerCalibration,
+        left_stick_error: Option<f32>,
+        right_stick_error: Option<f32>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Edge of a stick reaching `x_reach` sideways and `y_reach` up and down, one sample per degree.
+    fn oval_samples(x_reach: f32, y_reach: f32) -> Vec<(f32, f32)> {
+        (0..360)
+            .map(|degree| (degree as f32).to_radians())
+            .map(|angle| (angle.cos() * x_reach, angle.sin() * y_reach))
+            .collect()
+    }
+
+    fn corrected_radius(stick: &StickCalibration, x: f32, y: f32) -> f32 {
+        let scale = stick.radial_scale(x, y);
+        (x * scale).hypot(y * scale)
+    }
+
+    #[test]
+    fn off_center_axis_reaches_full_scale_on_both_sides() {
+        let calibration = AxisCalibration {
+            center: 1000,
+            min: -20000,
+            max: 25000,
+        };
+
+        assert_eq!(calibration.apply(1000), 0);
+        assert_eq!(calibration.apply(25000), i16::MAX);
+        assert_eq!(calibration.apply(-20000), -i16::MAX);
+        assert_eq!(calibration.apply(13000), i16::MAX / 2);
+        assert_eq!(calibration.apply(-9500), -(i16::MAX / 2));
+        assert_eq!(calibration.apply(i16::MAX), i16::MAX);
+        assert_eq!(calibration.apply(i16::MIN), -i16::MAX);
+    }
+
+    #[test]
+    fn oval_stick_is_corrected_to_the_unit_circle() {
+        let stick = StickCalibration::from_samples(&oval_samples(1.0, 0.8));
+
+        assert!(stick.circularity_error() > 0.05);
+        assert!((corrected_radius(&stick, 1.0, 0.0) - 1.0).abs() < 0.01);
+        assert!((corrected_radius(&stick, 0.0, 0.8) - 1.0).abs() < 0.01);
+        assert!((corrected_radius(&stick, 0.0, -0.8) - 1.0).abs() < 0.01);
+
+        // Between two measured directions the reach is interpolated
+        let diagonal = oval_samples(1.0, 0.8)[45];
+        assert!(diagonal.0.hypot(diagonal.1) < 0.95);
+        assert!((corrected_radius(&stick, diagonal.0, diagonal.1) - 1.0).abs() < 0.05);
+
+        // Halfway out stays halfway
+        assert!((corrected_radius(&stick, 0.0, 0.4) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn round_stick_is_left_alone() {
+        let stick = StickCalibration::from_samples(&oval_samples(1.0, 1.0));
+
+        assert!(stick.circularity_error() < 0.01);
+        assert!((stick.radial_scale(0.6, 0.6) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn unreached_sectors_stay_at_full_reach() {
+        // Only the right half was rolled, the rest barely moved
+        let samples: Vec<(f32, f32)> = oval_samples(0.8, 0.8)
+            .into_iter()
+            .filter(|(x, _)| *x > 0.1)
+            .chain([(-0.2, 0.0), (0.0, 0.0)])
+            .collect();
+        let stick = StickCalibration::from_samples(&samples);
+
+        assert_eq!(stick.reach[STICK_REACH_SECTORS / 2], 1.0);
+        assert!((stick.reach[0] - 0.8).abs() < 0.01);
+        assert_eq!(stick.radial_scale(-0.5, 0.0), 1.0);
+        assert_eq!(stick.radial_scale(0.0, 0.0), 1.0);
+    }
+}
@@ -1,26 +1,370 @@
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-use super::mapping::Mapping;
+use super::{
+    calibration::ControllerCalibration,
+    gamepad::{ControllerIdentity, GamepadButton, LedColor},
+    mapping::{Action, DeadzoneSettings, Mapping, MappingInput, StickDeadzones, StickType},
+};
+
+/// Controller seen at least once, recognized through its identity key when it reconnects.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/config.ts")]
+pub struct KnownDevice {
+    pub key: String,
+    pub alias: Option<String>,
+    pub identity: ControllerIdentity,
+    #[serde(default)]
+    pub led_color: Option<LedColor>,
+    #[serde(default)]
+    pub calibration: Option<ControllerCalibration>,
+}
+
+impl KnownDevice {
+    pub fn new(identity: ControllerIdentity) -> Self {
+        Self {
+            key: identity.key(),
+            alias: None,
+            identity,
+            led_color: None,
+            calibration: None,
+        }
+    }
+}
+
+/// Lightbar colours reflecting the mapping state, the active profile's and the controller's own colours win over `active`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/config.ts")]
+pub struct LedColors {
+    pub active: Option<LedColor>,
+    pub paused: Option<LedColor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/config.ts")]
+pub enum LayerMode {
+    /// Active while the button is held
+    Hold,
+    /// A press turns the layer on, the next one turns it off
+    Toggle,
+    /// Active for the next button pressed, until that button is released
+    OneShot,
+}
+
+/// Mappings stacked over the base mappings while active, `button` only switches the layer and no longer fires its own mappings.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/config.ts")]
+pub struct MappingLayer {
+    pub id: String,
+    pub name: String,
+    pub button: GamepadButton,
+    pub mode: LayerMode,
+    pub mappings: Vec<Mapping>,
+}
+
+/// Format of the stored config, bumped when a stored value changes meaning.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Id given to the profile holding the mappings made before any profile was created.
+pub const DEFAULT_PROFILE_ID: &str = "default";
+
+/// Named set of mappings and layers, switched as a whole.
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/config.ts")]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub mappings: Vec<Mapping>,
+    #[serde(default)]
+    pub layers: Vec<MappingLayer>,
+    /// Lightbar colour while the profile is active, wins over the controller's own colour
+    #[serde(default)]
+    pub led_color: Option<LedColor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/config.ts")]
+pub struct ProfileSummary {
+    pub id: String,
+    pub name: String,
+    pub led_color: Option<LedColor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/config.ts")]
+pub struct ProfileList {
+    pub profiles: Vec<ProfileSummary>,
+    pub active: String,
+}
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone, TS)]
 #[serde(rename_all = "camelCase")]
 #[ts(export, export_to = "../../src/ts/bindings/config.ts")]
 pub struct Config {
+    /// Missing from the configs stored before versioning, read as 0
+    #[serde(default)]
+    pub version: u32,
     pub mapping_active_on_boot: bool,
     pub mappings: Vec<Mapping>,
     pub deadzone: f32,
     pub keyboard_layout: Option<String>,
+    #[serde(default)]
+    pub known_devices: Vec<KnownDevice>,
+    #[serde(default = "default_low_battery_threshold")]
+    pub low_battery_threshold: u8,
+    #[serde(default)]
+    pub led_colors: LedColors,
+    /// SDL mapping lines (gamecontrollerdb format) added on top of the SDL database
+    #[serde(default)]
+    pub custom_sdl_mappings: Vec<String>,
+    #[serde(default)]
+    pub stick_deadzones: StickDeadzones,
+    /// Layers that can be stacked over `mappings`
+    #[serde(default)]
+    pub layers: Vec<MappingLayer>,
+    /// Saved profiles, `mappings` and `layers` are the working copy of the active one
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    #[serde(default)]
+    pub active_profile: Option<String>,
+}
+
+fn default_low_battery_threshold() -> u8 {
+    15
 }
 
 impl Config {
     pub fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             mapping_active_on_boot: true,
             mappings: vec![],
             deadzone: 0.1,
             keyboard_layout: None,
+            known_devices: vec![],
+            low_battery_threshold: default_low_battery_threshold(),
+            led_colors: LedColors::default(),
+            custom_sdl_mappings: vec![],
+            stick_deadzones: StickDeadzones::default(),
+            layers: vec![],
+            profiles: vec![],
+            active_profile: None,
+        }
+    }
+
+    /// Upgrades a config stored by an older version, returns whether it changed.
+    pub fn migrate(&mut self) -> bool {
+        if self.version >= CONFIG_VERSION {
+            return false;
+        }
+
+        if self.version < 1 {
+            // Speeds were per 8ms tick (a hundredth of a step for scrolling), they are per second since version 1
+            for action in self.stored_mappings_mut().filter_map(Mapping::action_mut) {
+                match action {
+                    Action::MouseMoveDirection { speed, .. }
+                    | Action::MouseMoveStick { speed, .. } => {
+                        *speed = speed.saturating_mul(125);
+                    }
+                    Action::ScrollDirection { speed, .. } | Action::ScrollStick { speed } => {
+                        *speed = (*speed as u16 * 5 / 4).min(u8::MAX as u16) as u8;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.version = CONFIG_VERSION;
+        true
+    }
+
+    /// Every stored mapping: the working copy and the saved profiles, with their layers.
+    fn stored_mappings_mut(&mut self) -> impl Iterator<Item = &mut Mapping> {
+        let profiles = self
+            .profiles
+            .iter_mut()
+            .map(|profile| (&mut profile.mappings, &mut profile.layers));
+
+        std::iter::once((&mut self.mappings, &mut self.layers))
+            .chain(profiles)
+            .flat_map(|(mappings, layers)| {
+                mappings.iter_mut().chain(
+                    layers
+                        .iter_mut()
+                        .flat_map(|layer| layer.mappings.iter_mut()),
+                )
+            })
+    }
+
+    pub fn stick_deadzone(&self, stick: &StickType) -> DeadzoneSettings {
+        let settings = match stick {
+            StickType::LeftStick => self.stick_deadzones.left_stick,
+            StickType::RightStick => self.stick_deadzones.right_stick,
+            StickType::Gyro => None,
+        };
+
+        settings.unwrap_or_else(|| DeadzoneSettings::with_inner(self.deadzone))
+    }
+
+    /// Base mappings followed by the mappings of every layer.
+    pub fn all_mappings(&self) -> impl Iterator<Item = &Mapping> {
+        self.mappings
+            .iter()
+            .chain(self.layers.iter().flat_map(|layer| layer.mappings.iter()))
+    }
+
+    pub fn layer_buttons(&self) -> HashSet<GamepadButton> {
+        self.layers.iter().map(|layer| layer.button).collect()
+    }
+
+    /// Mappings in effect with `active_layers` (bottom to top). The topmost layer mapping an input wins,
+    /// the base mappings only apply to the inputs no active layer maps.
+    pub fn resolve_mappings(&self, active_layers: &[String]) -> Vec<Mapping> {
+        let layer_buttons = self.layer_buttons();
+
+        let stack = active_layers
+            .iter()
+            .rev()
+            .filter_map(|id| self.layers.iter().find(|layer| layer.id == *id))
+            .map(|layer| &layer.mappings)
+            .chain(std::iter::once(&self.mappings));
+
+        let mut covered: HashSet<MappingInput> = HashSet::new();
+        let mut resolved: Vec<Mapping> = Vec::new();
+
+        for mappings in stack {
+            resolved.extend(
+                mappings
+                    .iter()
+                    .filter(|mapping| !covered.contains(&mapping.input()))
+                    .filter(|mapping| match mapping.input() {
+                        MappingInput::Button(button) => !layer_buttons.contains(&button),
+                        _ => true,
+                    })
+                    .cloned(),
+            );
+
+            covered.extend(mappings.iter().map(Mapping::input));
         }
+
+        resolved
+    }
+
+    /// Turns the mappings made before any profile existed into the default profile, and makes sure one is active.
+    pub fn ensure_profiles(&mut self) {
+        if self.profiles.is_empty() {
+            self.profiles.push(Profile {
+                id: DEFAULT_PROFILE_ID.to_string(),
+                name: "Default".to_string(),
+                mappings: self.mappings.clone(),
+                layers: self.layers.clone(),
+                led_color: None,
+            });
+        }
+
+        if self.active_profile().is_none() {
+            self.active_profile = Some(self.profiles[0].id.clone());
+        }
+    }
+
+    pub fn active_profile(&self) -> Option<&Profile> {
+        self.profile(self.active_profile.as_ref()?)
+    }
+
+    pub fn profile(&self, id: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.id == id)
+    }
+
+    pub fn profile_mut(&mut self, id: &str) -> Option<&mut Profile> {
+        self.profiles.iter_mut().find(|profile| profile.id == id)
+    }
+
+    pub fn profile_list(&self) -> ProfileList {
+        let mut config = self.clone();
+        config.ensure_profiles();
+
+        ProfileList {
+            profiles: config
+                .profiles
+                .iter()
+                .map(|profile| ProfileSummary {
+                    id: profile.id.clone(),
+                    name: profile.name.clone(),
+                    led_color: profile.led_color,
+                })
+                .collect(),
+            active: config.active_profile.unwrap_or_default(),
+        }
+    }
+
+    /// Writes the working copy back into the active profile.
+    pub fn save_active_profile(&mut self) {
+        let mappings = self.mappings.clone();
+        let layers = self.layers.clone();
+
+        if let Some(id) = self.active_profile.clone()
+            && let Some(profile) = self.profile_mut(&id)
+        {
+            profile.mappings = mappings;
+            profile.layers = layers;
+        }
+    }
+
+    /// Saves the active profile then loads `id` into the working copy.
+    pub fn activate_profile(&mut self, id: &str) -> Result<(), String> {
+        self.ensure_profiles();
+
+        let profile = self
+            .profile(id)
+            .cloned()
+            .ok_or(format!("Unknown profile: {}", id))?;
+
+        self.save_active_profile();
+        self.mappings = profile.mappings;
+        self.layers = profile.layers;
+        self.active_profile = Some(profile.id);
+
+        Ok(())
+    }
+
+    /// Profile `offset` places away from the active one, wrapping around the list.
+    pub fn adjacent_profile(&self, offset: isize) -> String {
+        let list = self.profile_list();
+        let index = list
+            .profiles
+            .iter()
+            .position(|profile| profile.id == list.active)
+            .unwrap_or(0);
+
+        let adjacent = (index as isize + offset).rem_euclid(list.profiles.len() as isize);
+        list.profiles[adjacent as usize].id.clone()
+    }
+
+    /// First `profile-N` id not taken yet.
+    pub fn new_profile_id(&self) -> String {
+        (1..)
+            .map(|n| format!("profile-{}", n))
+            .find(|id| self.profile(id).is_none())
+            .unwrap()
+    }
+
+    pub fn known_device(&self, key: &str) -> Option<&KnownDevice> {
+        self.known_devices.iter().find(|device| device.key == key)
+    }
+
+    pub fn known_device_mut(&mut self, key: &str) -> Option<&mut KnownDevice> {
+        self.known_devices
+            .iter_mut()
+            .find(|device| device.key == key)
     }
 
     pub fn set(&mut self, key: &str, value: serde_json::Value) {
@@ -28,9 +372,104 @@ impl Config {
             "mapping_active_on_boot" => {
                 self.mapping_active_on_boot = serde_json::from_value(value).unwrap_or(true)
             }
-            "mappings" => self.mappings = serde_json::from_value(value).unwrap(),
+            "mappings" => {
+                self.mappings = serde_json::from_value(value).unwrap();
+                self.save_active_profile();
+            }
             "deadzone" => self.deadzone = serde_json::from_value(value).unwrap_or(0.1),
+            "stick_deadzones" => {
+                self.stick_deadzones = serde_json::from_value(value).unwrap_or_default()
+            }
+            "low_battery_threshold" => {
+                self.low_battery_threshold = serde_json::from_value(value)
+                    .unwrap_or_else(|_| default_low_battery_threshold())
+            }
+            "led_colors" => self.led_colors = serde_json::from_value(value).unwrap_or_default(),
+            "custom_sdl_mappings" => {
+                self.custom_sdl_mappings = serde_json::from_value(value).unwrap_or_default()
+            }
+            "layers" => {
+                self.layers = serde_json::from_value(value).unwrap_or_default();
+                self.save_active_profile();
+            }
+            "known_devices" => {
+                self.known_devices = serde_json::from_value(value).unwrap_or_default()
+            }
             _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speeds(mappings: &[Mapping]) -> Vec<u16> {
+        mappings
+            .iter()
+            .filter_map(Mapping::action)
+            .filter_map(|action| match action {
+                Action::MouseMoveDirection { speed, .. } | Action::MouseMoveStick { speed, .. } => {
+                    Some(*speed)
+                }
+                Action::ScrollDirection { speed, .. } | Action::ScrollStick { speed } => {
+                    Some(*speed as u16)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unversioned_cursor_speeds_become_pixels_per_second() {
+        let mapping = |id: &str, speed: u16| {
+            serde_json::json!({
+                "type": "buttonPressed",
+                "id": id,
+                "button": "dPadRight",
+                "action": { "type": "mouseMoveDirection", "direction": "right", "speed": speed },
+            })
+        };
+        let scroll = |id: &str, speed: u8| {
+            serde_json::json!({
+                "type": "buttonPressed",
+                "id": id,
+                "button": "dPadDown",
+                "action": { "type": "scrollDirection", "direction": "down", "speed": speed },
+            })
+        };
+        let mut config: Config = serde_json::from_value(serde_json::json!({
+            "mappingActiveOnBoot": true,
+            "mappings": [mapping("base", 10), scroll("scroll", 40)],
+            "deadzone": 0.1,
+            "keyboardLayout": null,
+            "layers": [{
+                "id": "fn",
+                "name": "Fn",
+                "button": "leftShoulder",
+                "mode": "hold",
+                "mappings": [mapping("layer", 4)],
+            }],
+            "profiles": [{
+                "id": "default",
+                "name": "Default",
+                "mappings": [mapping("base", 10), scroll("fast", 250)],
+            }],
+        }))
+        .unwrap();
+
+        assert!(config.migrate());
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(speeds(&config.mappings), vec![1250, 50]);
+        assert_eq!(speeds(&config.layers[0].mappings), vec![500]);
+        assert_eq!(speeds(&config.profiles[0].mappings), vec![1250, 255]);
+
+        assert!(!config.migrate());
+        assert_eq!(speeds(&config.mappings), vec![1250, 50]);
+    }
+
+    #[test]
+    fn new_configs_are_not_migrated() {
+        assert!(!Config::default().migrate());
+    }
+}
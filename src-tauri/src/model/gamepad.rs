@@ -1,12 +1,29 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
 
-use sdl3::{gamepad::Gamepad, sensor::SensorType};
+use sdl3::{gamepad::Gamepad, joystick::Joystick, sensor::SensorType};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
+use super::calibration::ControllerCalibration;
+
+pub const MAX_TOUCHPAD_FINGERS: usize = 2;
+
+/// Origin of the input event timestamps.
+static INPUT_CLOCK_START: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Serialized as a camelCase name, buttons SDL doesn't name yet are serialized as `unknown<index>`
+/// and raw joystick buttons as `joystickButton<index>`, so they can still be used as map keys and targeted by mappings.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
-#[serde(rename_all = "camelCase")]
-#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+#[serde(into = "String", try_from = "String")]
+#[ts(
+    rename_all = "camelCase",
+    export,
+    export_to = "../../src/ts/bindings/gamepad.ts"
+)]
 pub enum GamepadButton {
     A,
     B,
@@ -23,17 +40,130 @@ pub enum GamepadButton {
     DPadDown,
     DPadLeft,
     DPadRight,
+    /// Share on Xbox Series, microphone mute on DualSense, capture on Switch. SDL reports mic mute and share
+    /// as this button, they have no variant of their own.
     Misc1,
+    /// Extra buttons, e.g. the DualSense Edge function buttons or the Switch 2 C button
+    Misc2,
+    Misc3,
+    Misc4,
+    Misc5,
+    Misc6,
+    /// Same numbering as SDL mapping strings: 1 and 3 are the right paddles, 2 and 4 the left ones
     Paddle1,
     Paddle2,
     Paddle3,
     Paddle4,
     Touchpad,
+    /// Only read back from stored mappings, every SDL gamepad button has its own variant
+    #[ts(type = "`unknown${number}`")]
+    Unknown(u8),
+    #[ts(type = "`joystickButton${number}`")]
+    JoystickButton(u8),
+}
+
+impl GamepadButton {
+    pub const KNOWN: [GamepadButton; 26] = [
+        GamepadButton::A,
+        GamepadButton::B,
+        GamepadButton::X,
+        GamepadButton::Y,
+        GamepadButton::Back,
+        GamepadButton::Guide,
+        GamepadButton::Start,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+        GamepadButton::Misc1,
+        GamepadButton::Misc2,
+        GamepadButton::Misc3,
+        GamepadButton::Misc4,
+        GamepadButton::Misc5,
+        GamepadButton::Misc6,
+        GamepadButton::Paddle1,
+        GamepadButton::Paddle2,
+        GamepadButton::Paddle3,
+        GamepadButton::Paddle4,
+        GamepadButton::Touchpad,
+    ];
+
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            GamepadButton::A => Some("a"),
+            GamepadButton::B => Some("b"),
+            GamepadButton::X => Some("x"),
+            GamepadButton::Y => Some("y"),
+            GamepadButton::Back => Some("back"),
+            GamepadButton::Guide => Some("guide"),
+            GamepadButton::Start => Some("start"),
+            GamepadButton::LeftStick => Some("leftStick"),
+            GamepadButton::RightStick => Some("rightStick"),
+            GamepadButton::LeftShoulder => Some("leftShoulder"),
+            GamepadButton::RightShoulder => Some("rightShoulder"),
+            GamepadButton::DPadUp => Some("dPadUp"),
+            GamepadButton::DPadDown => Some("dPadDown"),
+            GamepadButton::DPadLeft => Some("dPadLeft"),
+            GamepadButton::DPadRight => Some("dPadRight"),
+            GamepadButton::Misc1 => Some("misc1"),
+            GamepadButton::Misc2 => Some("misc2"),
+            GamepadButton::Misc3 => Some("misc3"),
+            GamepadButton::Misc4 => Some("misc4"),
+            GamepadButton::Misc5 => Some("misc5"),
+            GamepadButton::Misc6 => Some("misc6"),
+            GamepadButton::Paddle1 => Some("paddle1"),
+            GamepadButton::Paddle2 => Some("paddle2"),
+            GamepadButton::Paddle3 => Some("paddle3"),
+            GamepadButton::Paddle4 => Some("paddle4"),
+            GamepadButton::Touchpad => Some("touchpad"),
+            GamepadButton::Unknown(_) | GamepadButton::JoystickButton(_) => None,
+        }
+    }
+}
+
+impl From<GamepadButton> for String {
+    fn from(button: GamepadButton) -> Self {
+        match button {
+            GamepadButton::Unknown(index) => format!("unknown{}", index),
+            GamepadButton::JoystickButton(index) => format!("joystickButton{}", index),
+            button => button.name().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+impl TryFrom<String> for GamepadButton {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some(button) = GamepadButton::KNOWN
+            .into_iter()
+            .find(|button| button.name() == Some(value.as_str()))
+        {
+            return Ok(button);
+        }
+
+        if let Some(index) = parse_indexed(&value, "joystickButton") {
+            return Ok(GamepadButton::JoystickButton(index));
+        }
+
+        parse_indexed(&value, "unknown")
+            .map(GamepadButton::Unknown)
+            .ok_or(format!("Unknown gamepad button: {}", value))
+    }
 }
 
+/// Serialized as a camelCase name, raw joystick axes are serialized as `joystickAxis<index>`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
-#[serde(rename_all = "camelCase")]
-#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+#[serde(into = "String", try_from = "String")]
+#[ts(
+    rename_all = "camelCase",
+    export,
+    export_to = "../../src/ts/bindings/gamepad.ts"
+)]
 pub enum GamepadAxis {
     LeftX,
     LeftY,
@@ -41,6 +171,286 @@ pub enum GamepadAxis {
     RightY,
     TriggerLeft,
     TriggerRight,
+    #[ts(type = "`joystickAxis${number}`")]
+    JoystickAxis(u8),
+}
+
+impl GamepadAxis {
+    pub const KNOWN: [GamepadAxis; 6] = [
+        GamepadAxis::LeftX,
+        GamepadAxis::LeftY,
+        GamepadAxis::RightX,
+        GamepadAxis::RightY,
+        GamepadAxis::TriggerLeft,
+        GamepadAxis::TriggerRight,
+    ];
+
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            GamepadAxis::LeftX => Some("leftX"),
+            GamepadAxis::LeftY => Some("leftY"),
+            GamepadAxis::RightX => Some("rightX"),
+            GamepadAxis::RightY => Some("rightY"),
+            GamepadAxis::TriggerLeft => Some("triggerLeft"),
+            GamepadAxis::TriggerRight => Some("triggerRight"),
+            GamepadAxis::JoystickAxis(_) => None,
+        }
+    }
+}
+
+impl From<GamepadAxis> for String {
+    fn from(axis: GamepadAxis) -> Self {
+        match axis {
+            GamepadAxis::JoystickAxis(index) => format!("joystickAxis{}", index),
+            axis => axis.name().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+impl TryFrom<String> for GamepadAxis {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some(axis) = GamepadAxis::KNOWN
+            .into_iter()
+            .find(|axis| axis.name() == Some(value.as_str()))
+        {
+            return Ok(axis);
+        }
+
+        parse_indexed(&value, "joystickAxis")
+            .map(GamepadAxis::JoystickAxis)
+            .ok_or(format!("Unknown gamepad axis: {}", value))
+    }
+}
+
+fn parse_indexed(value: &str, prefix: &str) -> Option<u8> {
+    value
+        .strip_prefix(prefix)
+        .and_then(|index| index.parse().ok())
+}
+
+/// Position of a joystick hat switch (d-pad on devices without a gamepad mapping).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub enum HatPosition {
+    #[default]
+    Centered,
+    Up,
+    Right,
+    Down,
+    Left,
+    RightUp,
+    RightDown,
+    LeftUp,
+    LeftDown,
+}
+
+/// Gamepads have a standard layout, joysticks (flight sticks, pedals, button boxes) only expose numbered inputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub enum DeviceKind {
+    #[default]
+    Gamepad,
+    Joystick,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub enum MotionSensor {
+    Gyroscope,
+    Accelerometer,
+}
+
+/// Gyroscope readings are in radians per second, accelerometer readings in m/s².
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub struct SensorReading {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    #[ts(type = "number")]
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub struct MotionState {
+    pub has_gyro: bool,
+    pub has_accel: bool,
+    pub gyro: Option<SensorReading>,
+    pub accel: Option<SensorReading>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub enum ControllerType {
+    Xbox360,
+    XboxOne,
+    Ps3,
+    Ps4,
+    Ps5,
+    SwitchPro,
+    SwitchJoyCon,
+    #[default]
+    Unknown,
+}
+
+impl ControllerType {
+    pub fn from_ids(vendor_id: u16, product_id: u16) -> Self {
+        match (vendor_id, product_id) {
+            (0x045e, 0x028e | 0x028f | 0x0719) => ControllerType::Xbox360,
+            (0x045e, _) => ControllerType::XboxOne,
+            (0x054c, 0x0268) => ControllerType::Ps3,
+            (0x054c, 0x05c4 | 0x09cc | 0x0ba0) => ControllerType::Ps4,
+            (0x054c, 0x0ce6 | 0x0df2) => ControllerType::Ps5,
+            (0x057e, 0x2009) => ControllerType::SwitchPro,
+            (0x057e, 0x2006 | 0x2007) => ControllerType::SwitchJoyCon,
+            _ => ControllerType::Unknown,
+        }
+    }
+}
+
+/// Identifies a physical controller across reconnects, unlike the SDL instance id.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub struct ControllerIdentity {
+    pub guid: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub serial: Option<String>,
+    pub controller_type: ControllerType,
+}
+
+impl ControllerIdentity {
+    pub fn new(guid: String, vendor_id: u16, product_id: u16, serial: Option<String>) -> Self {
+        Self {
+            guid,
+            vendor_id,
+            product_id,
+            serial,
+            controller_type: ControllerType::from_ids(vendor_id, product_id),
+        }
+    }
+
+    /// Key used to recognize the controller in the config, the serial disambiguates identical models.
+    pub fn key(&self) -> String {
+        match &self.serial {
+            Some(serial) if !serial.is_empty() => format!("{}:{}", self.guid, serial),
+            _ => self.guid.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub enum ConnectionType {
+    Wired,
+    Wireless,
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub enum BatteryStatus {
+    OnBattery,
+    Charging,
+    Charged,
+    NoBattery,
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub struct PowerState {
+    pub connection: ConnectionType,
+    pub battery_status: BatteryStatus,
+    pub battery_percent: Option<u8>,
+}
+
+impl PowerState {
+    pub fn is_low(&self, threshold: u8) -> bool {
+        self.battery_status == BatteryStatus::OnBattery
+            && self
+                .battery_percent
+                .is_some_and(|percent| percent <= threshold)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub enum InputEventKind {
+    ButtonDown { button: GamepadButton },
+    ButtonUp { button: GamepadButton },
+    AxisChanged { axis: GamepadAxis, value: i16 },
+}
+
+/// Single input change, published in order next to the gamepads snapshot so quick taps are never coalesced.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub struct InputEvent {
+    pub gamepad_id: u32,
+    /// Microseconds since the app started
+    #[ts(type = "number")]
+    pub timestamp: u64,
+    pub kind: InputEventKind,
+}
+
+impl InputEvent {
+    pub fn new(gamepad_id: u32, kind: InputEventKind, at: Instant) -> Self {
+        Self {
+            gamepad_id,
+            timestamp: at.saturating_duration_since(*INPUT_CLOCK_START).as_micros() as u64,
+            kind,
+        }
+    }
+
+    pub fn instant(&self) -> Instant {
+        *INPUT_CLOCK_START + Duration::from_micros(self.timestamp)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub struct LowBatteryWarning {
+    pub gamepad_id: u32,
+    pub name: String,
+    pub battery_percent: u8,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub struct LedColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// Finger on the touchpad, positions are normalized between 0 and 1 from the top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/gamepad.ts")]
+pub struct TouchpadFinger {
+    pub finger: u8,
+    pub x: f32,
+    pub y: f32,
+    pub pressure: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -53,61 +463,112 @@ pub struct GamepadState {
     buttons: HashMap<GamepadButton, bool>,
     #[ts(type = "Record<GamepadAxis, number>")]
     axis: HashMap<GamepadAxis, i16>,
+    identity: ControllerIdentity,
+    player_slot: u8,
+    power: PowerState,
+    motion: MotionState,
+    touchpad_fingers: Vec<TouchpadFinger>,
+    kind: DeviceKind,
+    hats: Vec<HatPosition>,
+    /// Axis values as reported by the device, before calibration
+    #[serde(skip)]
+    #[ts(skip)]
+    raw_axis: HashMap<GamepadAxis, i16>,
+    #[serde(skip)]
+    #[ts(skip)]
+    calibration: Option<ControllerCalibration>,
 }
 
 impl GamepadState {
     pub fn new(id: u32, name: String) -> Self {
-        let mut buttons: HashMap<GamepadButton, bool> = HashMap::with_capacity(21);
-        let mut axis: HashMap<GamepadAxis, i16> = HashMap::with_capacity(6);
-
-        buttons.insert(GamepadButton::A, false);
-        buttons.insert(GamepadButton::B, false);
-        buttons.insert(GamepadButton::X, false);
-        buttons.insert(GamepadButton::Y, false);
-        buttons.insert(GamepadButton::Back, false);
-        buttons.insert(GamepadButton::Guide, false);
-        buttons.insert(GamepadButton::Start, false);
-        buttons.insert(GamepadButton::LeftStick, false);
-        buttons.insert(GamepadButton::RightStick, false);
-        buttons.insert(GamepadButton::LeftShoulder, false);
-        buttons.insert(GamepadButton::RightShoulder, false);
-        buttons.insert(GamepadButton::DPadUp, false);
-        buttons.insert(GamepadButton::DPadDown, false);
-        buttons.insert(GamepadButton::DPadLeft, false);
-        buttons.insert(GamepadButton::DPadRight, false);
-        buttons.insert(GamepadButton::Misc1, false);
-        buttons.insert(GamepadButton::Paddle1, false);
-        buttons.insert(GamepadButton::Paddle2, false);
-        buttons.insert(GamepadButton::Paddle3, false);
-        buttons.insert(GamepadButton::Paddle4, false);
-        buttons.insert(GamepadButton::Touchpad, false);
-
-        axis.insert(GamepadAxis::LeftX, 0);
-        axis.insert(GamepadAxis::LeftY, 0);
-        axis.insert(GamepadAxis::RightX, 0);
-        axis.insert(GamepadAxis::RightY, 0);
-        axis.insert(GamepadAxis::TriggerLeft, 0);
-        axis.insert(GamepadAxis::TriggerRight, 0);
+        let mut buttons: HashMap<GamepadButton, bool> =
+            HashMap::with_capacity(GamepadButton::KNOWN.len());
+        let mut axis: HashMap<GamepadAxis, i16> = HashMap::with_capacity(GamepadAxis::KNOWN.len());
+
+        for button in GamepadButton::KNOWN {
+            buttons.insert(button, false);
+        }
+
+        for gamepad_axis in GamepadAxis::KNOWN {
+            axis.insert(gamepad_axis, 0);
+        }
 
         Self {
             id,
             name,
             buttons,
             axis,
+            identity: ControllerIdentity::default(),
+            player_slot: 0,
+            power: PowerState::default(),
+            motion: MotionState::default(),
+            touchpad_fingers: Vec::with_capacity(MAX_TOUCHPAD_FINGERS),
+            kind: DeviceKind::Gamepad,
+            hats: Vec::new(),
+            raw_axis: HashMap::new(),
+            calibration: None,
         }
     }
 
+    /// State of a device without a gamepad mapping, its inputs are `JoystickButton`, `JoystickAxis` and hats.
+    pub fn new_joystick(id: u32, name: String, buttons: u8, axes: u8, hats: u8) -> Self {
+        let mut state = Self::new(id, name);
+
+        state.kind = DeviceKind::Joystick;
+        state.buttons = (0..buttons)
+            .map(|index| (GamepadButton::JoystickButton(index), false))
+            .collect();
+        state.axis = (0..axes)
+            .map(|index| (GamepadAxis::JoystickAxis(index), 0))
+            .collect();
+        state.hats = vec![HatPosition::Centered; hats as usize];
+
+        state
+    }
+
+    pub fn from_sdl_joystick(joystick: &Joystick) -> Self {
+        let mut state = Self::new_joystick(
+            joystick.id(),
+            joystick.name(),
+            joystick.num_buttons().min(u8::MAX as u32) as u8,
+            joystick.num_axes().min(u8::MAX as u32) as u8,
+            joystick.num_hats().min(u8::MAX as u32) as u8,
+        );
+
+        state.identity = ControllerIdentity::new(
+            joystick.guid().string(),
+            joystick.vendor_id().unwrap_or(0),
+            joystick.product_id().unwrap_or(0),
+            joystick.serial_number(),
+        );
+
+        state
+    }
+
     pub fn from_sdl_gamepad(gamepad: &Gamepad) -> Self {
+        let mut state = Self::new(gamepad.id().unwrap(), gamepad.name().unwrap());
+
+        // The mapping string starts with the joystick GUID
+        let guid = gamepad
+            .mapping()
+            .split(',')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        state.identity = ControllerIdentity::new(
+            guid,
+            gamepad.vendor_id().unwrap_or(0),
+            gamepad.product_id().unwrap_or(0),
+            gamepad.serial_number(),
+        );
+
         unsafe {
-            println!(
-                "Gamepad has gyro: {:?}",
-                gamepad.has_sensor(SensorType::Gyroscope)
-            );
+            state.motion.has_gyro = gamepad.has_sensor(SensorType::Gyroscope);
+            state.motion.has_accel = gamepad.has_sensor(SensorType::Accelerometer);
         }
 
-        Self::new(gamepad.id().unwrap(), gamepad.name().unwrap())
-
-        // print support hidapi
+        state
     }
 
     pub fn set_button(&mut self, button: GamepadButton, pressed: bool) {
@@ -115,10 +576,110 @@ impl GamepadState {
     }
 
     pub fn set_axis(&mut self, axis: GamepadAxis, value: i16) {
+        self.raw_axis.insert(axis, value);
+
+        let value = match self.calibration.as_ref().and_then(|c| c.axis(&axis)) {
+            Some(calibration) => calibration.apply(value),
+            None => value,
+        };
+
         self.axis
             .insert(axis, value.clamp(i16::MIN + 1, i16::MAX - 1)); // prevent overflow errors
     }
 
+    /// Sets a value that went through the calibration already (input events), the raw value is left as is.
+    pub fn set_calibrated_axis(&mut self, axis: GamepadAxis, value: i16) {
+        self.axis.insert(axis, value);
+    }
+
+    /// Replaces the calibration and recomputes the axis values from the last raw ones.
+    pub fn set_calibration(&mut self, calibration: Option<ControllerCalibration>) {
+        self.calibration = calibration;
+
+        let raw_axis: Vec<(GamepadAxis, i16)> = self
+            .raw_axis
+            .iter()
+            .map(|(axis, value)| (*axis, *value))
+            .collect();
+
+        for (axis, value) in raw_axis {
+            self.set_axis(axis, value);
+        }
+    }
+
+    pub fn calibration(&self) -> Option<&ControllerCalibration> {
+        self.calibration.as_ref()
+    }
+
+    pub fn set_hat(&mut self, hat: u8, position: HatPosition) {
+        if let Some(current) = self.hats.get_mut(hat as usize) {
+            *current = position;
+        }
+    }
+
+    pub fn set_identity(&mut self, identity: ControllerIdentity) {
+        self.identity = identity;
+    }
+
+    pub fn identity(&self) -> &ControllerIdentity {
+        &self.identity
+    }
+
+    pub fn set_player_slot(&mut self, slot: u8) {
+        self.player_slot = slot;
+    }
+
+    pub fn player_slot(&self) -> u8 {
+        self.player_slot
+    }
+
+    pub fn set_power(&mut self, power: PowerState) {
+        self.power = power;
+    }
+
+    pub fn power(&self) -> &PowerState {
+        &self.power
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_motion_support(&mut self, has_gyro: bool, has_accel: bool) {
+        self.motion.has_gyro = has_gyro;
+        self.motion.has_accel = has_accel;
+    }
+
+    pub fn set_sensor_reading(&mut self, sensor: MotionSensor, reading: SensorReading) {
+        match sensor {
+            MotionSensor::Gyroscope => {
+                self.motion.has_gyro = true;
+                self.motion.gyro = Some(reading);
+            }
+            MotionSensor::Accelerometer => {
+                self.motion.has_accel = true;
+                self.motion.accel = Some(reading);
+            }
+        }
+    }
+
+    pub fn set_touchpad_finger(&mut self, finger: TouchpadFinger, pressed: bool) {
+        self.touchpad_fingers.retain(|f| f.finger != finger.finger);
+
+        if pressed && (finger.finger as usize) < MAX_TOUCHPAD_FINGERS {
+            self.touchpad_fingers.push(finger);
+            self.touchpad_fingers.sort_by_key(|f| f.finger);
+        }
+    }
+
+    pub fn touchpad_fingers(&self) -> &[TouchpadFinger] {
+        &self.touchpad_fingers
+    }
+
+    pub fn motion(&self) -> &MotionState {
+        &self.motion
+    }
+
     pub fn id(&self) -> u32 {
         self.id
     }
@@ -127,18 +688,49 @@ impl GamepadState {
         &self.axis
     }
 
+    pub fn pressed_buttons(&self) -> Vec<GamepadButton> {
+        self.buttons
+            .iter()
+            .filter(|(_, pressed)| **pressed)
+            .map(|(button, _)| *button)
+            .collect()
+    }
+
     pub fn is_button_pressed(&self, button: &GamepadButton) -> bool {
         self.buttons.get(button).unwrap_or(&false).to_owned()
     }
 
+    pub fn axis_value(&self, axis: &GamepadAxis) -> i16 {
+        *self.axis.get(axis).unwrap_or(&0)
+    }
+
+    pub fn raw_axis_value(&self, axis: &GamepadAxis) -> i16 {
+        *self.raw_axis.get(axis).unwrap_or(&0)
+    }
+
     pub fn get_normalized_axis_value(&self, axis: &GamepadAxis) -> f32 {
-        // contrain coords between -1 and 1
-        let coord = *self.axis.get(axis).unwrap_or(&0);
+        let coord = self.axis_value(axis);
 
         if coord == 0 {
             return 0.0;
         }
 
-        (coord as f32 / i16::MAX as f32).clamp(-1.0, 1.0)
+        let value = normalize_axis(coord);
+
+        // Circularity is corrected on the whole stick, it needs the other axis
+        let Some((stick, x_axis, y_axis)) = self.calibration.as_ref().and_then(|c| c.stick(axis))
+        else {
+            return value;
+        };
+
+        let x = normalize_axis(self.axis_value(&x_axis));
+        let y = normalize_axis(self.axis_value(&y_axis));
+
+        value * stick.radial_scale(x, y)
     }
 }
+
+/// Constrains an axis value between -1 and 1.
+fn normalize_axis(coord: i16) -> f32 {
+    (coord as f32 / i16::MAX as f32).clamp(-1.0, 1.0)
+}
@@ -1,14 +1,17 @@
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
-use serde::{Deserialize, Serialize};
-use tauri::Url;
+use serde::{Deserialize, Deserializer, Serialize};
 use ts_rs::TS;
 
-use super::gamepad::{GamepadAxis, GamepadButton};
+use super::gamepad::{GamepadAxis, GamepadButton, GamepadState, LedColor};
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[serde(rename_all = "camelCase")]
 #[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+#[allow(dead_code)] // Only used by the frontend, through its binding
 pub enum BooleanOperator {
     And,
     Or,
@@ -38,26 +41,102 @@ pub enum Action {
     PressKeys { keys: Vec<String> },
     WriteText { text: String },
 
-    // Mouse actions - for buttons (with direction)
-    MouseMoveDirection { direction: Direction, speed: u8 },
+    // Mouse actions - for buttons (with direction), speed in pixels per second
+    MouseMoveDirection { direction: Direction, speed: u16 },
     MouseClick { button: MouseButton },
 
-    // Mouse actions - for sticks (all directions)
-    MouseMoveStick { mode: MouseMoveMode, speed: u8 },
+    // Mouse actions - for sticks (all directions), speed in pixels per second at full tilt
+    MouseMoveStick { mode: MouseMoveMode, speed: u16 },
 
-    // Scroll actions - for buttons (with direction)
+    // Mouse actions - for gyro
+    MouseMoveGyro { settings: GyroMouseSettings },
+
+    // Scroll actions - for buttons (with direction), speed in scroll steps per second
     ScrollDirection { direction: Direction, speed: u8 },
 
-    // Scroll actions - for sticks (all directions)
+    // Scroll actions - for sticks (all directions), speed in scroll steps per second at full tilt
     ScrollStick { speed: u8 },
 
+    // Controller actions
+    Rumble { rumble: Rumble },
+    SetLedColor { color: LedColor },
+
     // App actions
     ToogleMappingActive,
     ToogleVirtualKeyboard,
 
+    // Profile actions
+    SwitchProfile { profile: String },
+    NextProfile,
+    PreviousProfile,
+
     // System actions
     OpenWebsite { url: String },
     OpenFile { path: PathBuf },
+
+    // Sequence actions
+    Macro { sequence: MacroSequence },
+}
+
+/// Ordered steps run in the background, so a long macro doesn't hold the other mappings back.
+/// With `cancel_on_release` the macro stops when the input is released, otherwise it runs to completion.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub struct MacroSequence {
+    pub steps: Vec<MacroStep>,
+    #[serde(default)]
+    pub cancel_on_release: bool,
+}
+
+/// Keys left down by a macro are released when it ends. `Action` runs the action once,
+/// continuous ones (mouse movement, scrolling) are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub enum MacroStep {
+    KeyDown {
+        key: String,
+    },
+    KeyUp {
+        key: String,
+    },
+    Text {
+        text: String,
+    },
+    MouseClick {
+        button: MouseButton,
+    },
+    /// Relative move in pixels
+    MouseMove {
+        x: i32,
+        y: i32,
+    },
+    /// Scroll steps, positive is down and right
+    Scroll {
+        x: i32,
+        y: i32,
+    },
+    Wait {
+        ms: u32,
+    },
+    Action {
+        action: Box<Action>,
+    },
+}
+
+/// Motor and trigger strengths are percentages, the effect stops after `duration_ms`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub struct Rumble {
+    pub low_frequency: u8,
+    pub high_frequency: u8,
+    #[serde(default)]
+    pub left_trigger: u8,
+    #[serde(default)]
+    pub right_trigger: u8,
+    pub duration_ms: u16,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
@@ -87,12 +166,470 @@ pub enum MouseMoveMode {
     Absolute,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub enum GyroAxis {
+    Yaw,
+    Roll,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub enum RatchetMode {
+    Hold,
+    Toggle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub struct GyroRatchet {
+    pub button: GamepadButton,
+    pub mode: RatchetMode,
+}
+
+/// Sensitivity is in pixels per degree of rotation, deadzone in degrees per second.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub struct GyroMouseSettings {
+    pub sensitivity_x: u8,
+    pub sensitivity_y: u8,
+    pub horizontal_axis: GyroAxis,
+    pub deadzone: u8,
+    pub ratchet: Option<GyroRatchet>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
 #[serde(rename_all = "camelCase")]
 #[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
 pub enum StickType {
     LeftStick,  // LeftX (0) + LeftY (1)
     RightStick, // RightX (2) + RightY (3)
+    Gyro,       // Gyroscope angular velocity
+}
+
+/// `Axial` handles each axis on its own (square deadzone), `Radial` cuts a circle but keeps the raw
+/// magnitude outside of it, `ScaledRadial` cuts a circle and rescales the magnitude so it starts from zero.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub enum DeadzoneShape {
+    Axial,
+    Radial,
+    #[default]
+    ScaledRadial,
+}
+
+/// Values are fractions of the full stick range.
+/// `outer` is the magnitude from which the output is saturated, and `anti` the smallest output
+/// once out of the deadzone, to compensate for the deadzone of a game.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub struct DeadzoneSettings {
+    #[serde(default)]
+    pub shape: DeadzoneShape,
+    pub inner: f32,
+    #[serde(default = "default_outer_deadzone")]
+    pub outer: f32,
+    #[serde(default)]
+    pub anti: f32,
+}
+
+fn default_outer_deadzone() -> f32 {
+    1.0
+}
+
+impl DeadzoneSettings {
+    pub fn with_inner(inner: f32) -> Self {
+        Self {
+            shape: DeadzoneShape::default(),
+            inner,
+            outer: default_outer_deadzone(),
+            anti: 0.0,
+        }
+    }
+
+    /// Applies the deadzone to normalized stick coordinates, the result covers the full -1 to 1 range.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        match self.shape {
+            DeadzoneShape::Axial => (
+                self.rescale(x.abs()).copysign(x),
+                self.rescale(y.abs()).copysign(y),
+            ),
+            DeadzoneShape::Radial | DeadzoneShape::ScaledRadial => {
+                let magnitude = x.hypot(y);
+
+                if magnitude <= f32::EPSILON {
+                    return (0.0, 0.0);
+                }
+
+                let output = match self.shape {
+                    DeadzoneShape::Radial if magnitude >= self.inner => {
+                        (magnitude / self.outer.max(f32::EPSILON)).clamp(self.anti, 1.0)
+                    }
+                    DeadzoneShape::Radial => 0.0,
+                    _ => self.rescale(magnitude),
+                };
+
+                (x / magnitude * output, y / magnitude * output)
+            }
+        }
+    }
+
+    /// Maps a magnitude from `inner..outer` to `anti..1`, zero inside the deadzone.
+    fn rescale(&self, magnitude: f32) -> f32 {
+        if magnitude < self.inner || magnitude <= f32::EPSILON {
+            return 0.0;
+        }
+
+        let range = (self.outer - self.inner).max(f32::EPSILON);
+        let progress = ((magnitude - self.inner) / range).clamp(0.0, 1.0);
+
+        self.anti + (1.0 - self.anti) * progress
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub struct CurvePoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Reshapes an analog magnitude between 0 and 1, applied after the deadzone.
+/// `Power` raises it to `exponent` (above 1 gives more precision near the center),
+/// `SCurve` blends towards a smoothstep by `strength` (0 to 1),
+/// and `Custom` interpolates linearly between its points, sorted by `x` when read.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    Power {
+        exponent: f32,
+    },
+    SCurve {
+        strength: f32,
+    },
+    Custom {
+        #[serde(deserialize_with = "deserialize_curve_points")]
+        points: Vec<CurvePoint>,
+    },
+}
+
+impl ResponseCurve {
+    /// Applies the curve to a normalized value, keeping its sign.
+    pub fn apply(&self, value: f32) -> f32 {
+        let magnitude = value.abs().clamp(0.0, 1.0);
+
+        let output = match self {
+            ResponseCurve::Linear => magnitude,
+            ResponseCurve::Power { exponent } => magnitude.powf(exponent.max(f32::EPSILON)),
+            ResponseCurve::SCurve { strength } => {
+                let smooth = magnitude * magnitude * (3.0 - 2.0 * magnitude);
+                magnitude + strength.clamp(0.0, 1.0) * (smooth - magnitude)
+            }
+            ResponseCurve::Custom { points } => interpolate(points, magnitude),
+        };
+
+        output.clamp(0.0, 1.0).copysign(value)
+    }
+
+    /// Applies the curve to the stick magnitude, the direction is kept.
+    pub fn apply_to_stick(&self, x: f32, y: f32) -> (f32, f32) {
+        let magnitude = x.hypot(y);
+
+        if magnitude <= f32::EPSILON {
+            return (0.0, 0.0);
+        }
+
+        let scale = self.apply(magnitude.min(1.0)) / magnitude;
+
+        (x * scale, y * scale)
+    }
+}
+
+/// Drops the points that aren't numbers, clamps the others between 0 and 1 and sorts them by `x`.
+fn deserialize_curve_points<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<CurvePoint>, D::Error> {
+    let mut points: Vec<CurvePoint> = Vec::deserialize(deserializer)?
+        .into_iter()
+        .filter(|point: &CurvePoint| point.x.is_finite() && point.y.is_finite())
+        .map(|point| CurvePoint {
+            x: point.x.clamp(0.0, 1.0),
+            y: point.y.clamp(0.0, 1.0),
+        })
+        .collect();
+
+    points.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+    Ok(points)
+}
+
+fn interpolate(points: &[CurvePoint], value: f32) -> f32 {
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return value,
+    };
+
+    if value <= first.x {
+        return first.y;
+    }
+
+    points
+        .windows(2)
+        .find(|segment| value <= segment[1].x)
+        .map(|segment| {
+            let (start, end) = (segment[0], segment[1]);
+            let width = (end.x - start.x).max(f32::EPSILON);
+
+            start.y + (end.y - start.y) * (value - start.x) / width
+        })
+        .unwrap_or(last.y)
+}
+
+/// Deadzone of each stick, `None` uses the global `deadzone` with the default shape.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub struct StickDeadzones {
+    pub left_stick: Option<DeadzoneSettings>,
+    pub right_stick: Option<DeadzoneSettings>,
+}
+
+/// Restricts a mapping to one controller, either by its identity key or by its player slot (starting at 1).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub enum ControllerTarget {
+    Device { key: String },
+    PlayerSlot { slot: u8 },
+}
+
+impl ControllerTarget {
+    pub fn matches(&self, gamepad: &GamepadState) -> bool {
+        match self {
+            ControllerTarget::Device { key } => gamepad.identity().key() == *key,
+            ControllerTarget::PlayerSlot { slot } => gamepad.player_slot() == *slot,
+        }
+    }
+}
+
+/// Fastest turbo rate. A press and a release each last at least one 8ms tick of the mapping loop,
+/// so close to it an extreme duty cycle slows the turbo down.
+pub const MAX_TURBO_RATE: u8 = 30;
+
+/// Repeats a press-triggered action `rate` times per second, the output stays down for `duty_cycle` percent of each period.
+/// With `toggle`, a press latches the turbo on and the next press turns it off.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub struct TurboSettings {
+    pub rate: u8,
+    pub duty_cycle: u8,
+    #[serde(default)]
+    pub toggle: bool,
+}
+
+impl TurboSettings {
+    pub fn period(&self) -> Duration {
+        Duration::from_secs_f32(1.0 / self.rate.clamp(1, MAX_TURBO_RATE) as f32)
+    }
+
+    /// Lengths of the down and up phases, each lasts at least `min_phase` so none falls between two ticks.
+    pub fn phases(&self, min_phase: Duration) -> (Duration, Duration) {
+        let period = self.period();
+        // Always leave a down and an up phase, otherwise nothing would repeat
+        let down = period.mul_f32(self.duty_cycle.clamp(1, 99) as f32 / 100.0);
+
+        (down.max(min_phase), (period - down).max(min_phase))
+    }
+}
+
+/// When a button mapping fires, durations are in milliseconds.
+/// A button can carry one mapping per trigger, e.g. tap B for Escape and hold B for Alt+F4.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub enum ButtonTrigger {
+    /// As soon as the button is pressed, the action lasts while it is held
+    #[default]
+    Press,
+    /// On release, if the button was held less than `ms`
+    Tap { ms: u32 },
+    /// Once the button has been held for `ms`, the action lasts while it is held
+    Hold { ms: u32 },
+    /// On the second press, if it comes less than `ms` after the first release
+    DoubleTap { ms: u32 },
+    /// On release
+    Release,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerOutcome {
+    Idle,
+    /// The action starts and lasts until `Stop`
+    Start,
+    Stop,
+    /// The action fires and is released right away
+    Pulse,
+}
+
+/// Timing state of one button mapping, driven by the instants passed to `update` so it never reads the clock itself.
+#[derive(Debug, Clone, Default)]
+pub struct ButtonTriggerTracker {
+    was_pressed: bool,
+    pressed_at: Option<Instant>,
+    holding: bool,
+    last_release: Option<Instant>,
+    /// The current press completes a double tap
+    second_press: bool,
+    pending_tap: Option<Instant>,
+    deadline: Option<Instant>,
+}
+
+impl ButtonTriggerTracker {
+    /// Feeds the button state at `now`. `tap_delay` postpones taps when the button also has a double tap,
+    /// the tap is dropped if the second press comes within that delay.
+    pub fn update(
+        &mut self,
+        trigger: &ButtonTrigger,
+        pressed: bool,
+        now: Instant,
+        tap_delay: Option<Duration>,
+    ) -> TriggerOutcome {
+        let just_pressed = pressed && !self.was_pressed;
+        let just_released = !pressed && self.was_pressed;
+        self.was_pressed = pressed;
+
+        if just_pressed {
+            self.pressed_at = Some(now);
+        }
+
+        let held_for = self
+            .pressed_at
+            .map(|pressed_at| now.saturating_duration_since(pressed_at));
+
+        let outcome = match *trigger {
+            ButtonTrigger::Press => match (just_pressed, just_released) {
+                (true, _) => TriggerOutcome::Start,
+                (_, true) => TriggerOutcome::Stop,
+                _ => TriggerOutcome::Idle,
+            },
+            ButtonTrigger::Hold { ms } => {
+                let held_long_enough = held_for.is_some_and(|held| held >= millis(ms));
+
+                if pressed && !self.holding && held_long_enough {
+                    self.holding = true;
+                    TriggerOutcome::Start
+                } else if !pressed && self.holding {
+                    self.holding = false;
+                    TriggerOutcome::Stop
+                } else {
+                    TriggerOutcome::Idle
+                }
+            }
+            ButtonTrigger::Tap { ms } => {
+                self.update_tap(ms, just_pressed, just_released, held_for, now, tap_delay)
+            }
+            ButtonTrigger::DoubleTap { ms } => {
+                let in_window = self
+                    .last_release
+                    .is_some_and(|released| now.saturating_duration_since(released) <= millis(ms));
+
+                if just_pressed && in_window {
+                    self.last_release = None;
+                    self.second_press = true;
+                    TriggerOutcome::Pulse
+                } else {
+                    if just_released {
+                        // The release of the second press doesn't start another double tap
+                        match self.second_press {
+                            true => self.second_press = false,
+                            false => self.last_release = Some(now),
+                        }
+                    }
+
+                    TriggerOutcome::Idle
+                }
+            }
+            ButtonTrigger::Release => match just_released {
+                true => TriggerOutcome::Pulse,
+                false => TriggerOutcome::Idle,
+            },
+        };
+
+        if just_released {
+            self.pressed_at = None;
+        }
+
+        self.deadline = match *trigger {
+            ButtonTrigger::Hold { ms } if pressed && !self.holding => {
+                self.pressed_at.map(|pressed_at| pressed_at + millis(ms))
+            }
+            ButtonTrigger::Tap { .. } => self.pending_tap,
+            _ => None,
+        };
+
+        outcome
+    }
+
+    fn update_tap(
+        &mut self,
+        ms: u32,
+        just_pressed: bool,
+        just_released: bool,
+        held_for: Option<Duration>,
+        now: Instant,
+        tap_delay: Option<Duration>,
+    ) -> TriggerOutcome {
+        if let Some(fire_at) = self.pending_tap {
+            if now >= fire_at {
+                self.pending_tap = None;
+                return TriggerOutcome::Pulse;
+            }
+
+            // Pressed again in time, this is a double tap
+            if just_pressed {
+                self.pending_tap = None;
+                self.second_press = true;
+            }
+        }
+
+        if !just_released {
+            return TriggerOutcome::Idle;
+        }
+
+        let second_press = std::mem::take(&mut self.second_press);
+
+        if second_press || held_for.is_none_or(|held| held >= millis(ms)) {
+            return TriggerOutcome::Idle;
+        }
+
+        match tap_delay {
+            Some(delay) => {
+                self.pending_tap = Some(now + delay);
+                TriggerOutcome::Idle
+            }
+            None => TriggerOutcome::Pulse,
+        }
+    }
+
+    /// Instant at which `update` can fire without any input (hold reached or postponed tap).
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+}
+
+fn millis(ms: u32) -> Duration {
+    Duration::from_millis(ms as u64)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -104,6 +641,34 @@ pub struct ButtonMapping {
     pub action: Action,
     #[serde(default = "Vec::default")]
     pub conditions: Vec<ConditionType>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub target: Option<ControllerTarget>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub feedback: Option<Rumble>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub trigger: Option<ButtonTrigger>,
+    /// Only used with the `Press` trigger
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub turbo: Option<TurboSettings>,
+}
+
+/// Button mapping with none of the optional settings, tests set the ones they need with struct-update syntax.
+#[cfg(test)]
+pub fn button_mapping(id: &str, button: GamepadButton, action: Action) -> ButtonMapping {
+    ButtonMapping {
+        id: id.to_string(),
+        button,
+        action,
+        conditions: vec![],
+        target: None,
+        feedback: None,
+        trigger: None,
+        turbo: None,
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -115,7 +680,16 @@ pub struct AxisTriggerMapping {
     pub threshold: f32,
     #[serde(default = "Vec::default")]
     pub conditions: Vec<ConditionType>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub target: Option<ControllerTarget>,
     pub action: Action,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub feedback: Option<Rumble>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub curve: Option<ResponseCurve>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -127,6 +701,32 @@ pub struct AxisStickMapping {
     pub action: Action,
     #[serde(default = "Vec::default")]
     pub conditions: Vec<ConditionType>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub target: Option<ControllerTarget>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub curve: Option<ResponseCurve>,
+}
+
+/// Uses the touchpad like a laptop trackpad.
+/// `sensitivity` is the cursor travel across the full pad width in tens of pixels,
+/// `scroll_speed` the number of scroll steps across the full pad height with two fingers,
+/// and `right_click_zone` the percentage of the pad width from which clicks become right clicks.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/mapping.ts")]
+pub struct TouchpadMapping {
+    pub id: String,
+    pub sensitivity: u8,
+    pub scroll_speed: u8,
+    pub tap_to_click: bool,
+    pub right_click_zone: Option<u8>,
+    #[serde(default = "Vec::default")]
+    pub conditions: Vec<ConditionType>,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub target: Option<ControllerTarget>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -136,4 +736,339 @@ pub enum Mapping {
     ButtonPressed(ButtonMapping),
     AxisTrigger(AxisTriggerMapping),
     AxisStick(AxisStickMapping),
+    Touchpad(TouchpadMapping),
+}
+
+impl Mapping {
+    pub fn target(&self) -> Option<&ControllerTarget> {
+        match self {
+            Mapping::ButtonPressed(mapping) => mapping.target.as_ref(),
+            Mapping::AxisTrigger(mapping) => mapping.target.as_ref(),
+            Mapping::AxisStick(mapping) => mapping.target.as_ref(),
+            Mapping::Touchpad(mapping) => mapping.target.as_ref(),
+        }
+    }
+
+    pub fn applies_to(&self, gamepad: &GamepadState) -> bool {
+        self.target().is_none_or(|target| target.matches(gamepad))
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            Mapping::ButtonPressed(mapping) => &mapping.id,
+            Mapping::AxisTrigger(mapping) => &mapping.id,
+            Mapping::AxisStick(mapping) => &mapping.id,
+            Mapping::Touchpad(mapping) => &mapping.id,
+        }
+    }
+
+    pub fn action(&self) -> Option<&Action> {
+        match self {
+            Mapping::ButtonPressed(mapping) => Some(&mapping.action),
+            Mapping::AxisTrigger(mapping) => Some(&mapping.action),
+            Mapping::AxisStick(mapping) => Some(&mapping.action),
+            Mapping::Touchpad(_) => None,
+        }
+    }
+
+    pub fn action_mut(&mut self) -> Option<&mut Action> {
+        match self {
+            Mapping::ButtonPressed(mapping) => Some(&mut mapping.action),
+            Mapping::AxisTrigger(mapping) => Some(&mut mapping.action),
+            Mapping::AxisStick(mapping) => Some(&mut mapping.action),
+            Mapping::Touchpad(_) => None,
+        }
+    }
+
+    pub fn input(&self) -> MappingInput {
+        match self {
+            Mapping::ButtonPressed(mapping) => MappingInput::Button(mapping.button),
+            Mapping::AxisTrigger(mapping) => MappingInput::Axis(mapping.axis),
+            Mapping::AxisStick(mapping) => MappingInput::Stick(mapping.stick.clone()),
+            Mapping::Touchpad(_) => MappingInput::Touchpad,
+        }
+    }
+}
+
+/// Input a mapping listens to, a layer mapping hides the mappings of the same input below it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MappingInput {
+    Button(GamepadButton),
+    Axis(GamepadAxis),
+    Stick(StickType),
+    Touchpad,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// Feeds `(offset in ms, pressed)` samples and returns the outcomes that aren't `Idle`.
+    fn run(
+        trigger: ButtonTrigger,
+        tap_delay: Option<Duration>,
+        samples: &[(u64, bool)],
+    ) -> Vec<(u64, TriggerOutcome)> {
+        let start = Instant::now();
+        let mut tracker = ButtonTriggerTracker::default();
+
+        samples
+            .iter()
+            .map(|(at, pressed)| {
+                (
+                    *at,
+                    tracker.update(&trigger, *pressed, start + ms(*at), tap_delay),
+                )
+            })
+            .filter(|(_, outcome)| *outcome != TriggerOutcome::Idle)
+            .collect()
+    }
+
+    #[test]
+    fn tap_fires_on_a_short_release_only() {
+        let tap = ButtonTrigger::Tap { ms: 200 };
+
+        assert_eq!(
+            run(tap, None, &[(0, true), (100, false)]),
+            vec![(100, TriggerOutcome::Pulse)]
+        );
+        assert_eq!(run(tap, None, &[(0, true), (300, false)]), vec![]);
+    }
+
+    #[test]
+    fn hold_starts_once_the_duration_is_reached() {
+        let start = Instant::now();
+        let hold = ButtonTrigger::Hold { ms: 500 };
+        let mut tracker = ButtonTriggerTracker::default();
+
+        assert_eq!(
+            tracker.update(&hold, true, start, None),
+            TriggerOutcome::Idle
+        );
+        assert_eq!(tracker.deadline(), Some(start + ms(500)));
+        assert_eq!(
+            tracker.update(&hold, true, start + ms(499), None),
+            TriggerOutcome::Idle
+        );
+        assert_eq!(
+            tracker.update(&hold, true, start + ms(500), None),
+            TriggerOutcome::Start
+        );
+        assert_eq!(tracker.deadline(), None);
+        assert_eq!(
+            tracker.update(&hold, false, start + ms(800), None),
+            TriggerOutcome::Stop
+        );
+
+        assert_eq!(
+            run(hold, None, &[(0, true), (400, false), (450, false)]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn double_tap_fires_on_the_second_press_within_the_window() {
+        let double_tap = ButtonTrigger::DoubleTap { ms: 250 };
+
+        assert_eq!(
+            run(
+                double_tap,
+                None,
+                &[(0, true), (50, false), (200, true), (250, false)]
+            ),
+            vec![(200, TriggerOutcome::Pulse)]
+        );
+        assert_eq!(
+            run(
+                double_tap,
+                None,
+                &[(0, true), (50, false), (400, true), (450, false)]
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn release_fires_on_every_release() {
+        assert_eq!(
+            run(
+                ButtonTrigger::Release,
+                None,
+                &[(0, true), (1000, false), (1100, true), (1150, false)]
+            ),
+            vec![(1000, TriggerOutcome::Pulse), (1150, TriggerOutcome::Pulse)]
+        );
+    }
+
+    #[test]
+    fn tap_waits_for_the_double_tap_window() {
+        let start = Instant::now();
+        let tap = ButtonTrigger::Tap { ms: 200 };
+        let delay = Some(ms(250));
+        let mut tracker = ButtonTriggerTracker::default();
+
+        tracker.update(&tap, true, start, delay);
+        assert_eq!(
+            tracker.update(&tap, false, start + ms(100), delay),
+            TriggerOutcome::Idle
+        );
+        assert_eq!(tracker.deadline(), Some(start + ms(350)));
+        assert_eq!(
+            tracker.update(&tap, false, start + ms(349), delay),
+            TriggerOutcome::Idle
+        );
+        assert_eq!(
+            tracker.update(&tap, false, start + ms(350), delay),
+            TriggerOutcome::Pulse
+        );
+        assert_eq!(tracker.deadline(), None);
+
+        // A second press within the window is a double tap, the tap is dropped
+        assert_eq!(
+            run(
+                tap,
+                delay,
+                &[
+                    (0, true),
+                    (100, false),
+                    (200, true),
+                    (250, false),
+                    (600, false)
+                ]
+            ),
+            vec![]
+        );
+    }
+
+    fn assert_near(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-4 && (actual.1 - expected.1).abs() < 1e-4,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    fn deadzone(shape: DeadzoneShape, inner: f32, outer: f32, anti: f32) -> DeadzoneSettings {
+        DeadzoneSettings {
+            shape,
+            inner,
+            outer,
+            anti,
+        }
+    }
+
+    #[test]
+    fn deadzone_shapes_on_a_diagonal() {
+        let diagonal = (0.3, 0.3);
+
+        // Each axis on its own: 0.3 is an eighth of the way from 0.2 to 1
+        let axial = deadzone(DeadzoneShape::Axial, 0.2, 1.0, 0.0);
+        assert_near(axial.apply(diagonal.0, diagonal.1), (0.125, 0.125));
+        assert_near(axial.apply(0.3, 0.1), (0.125, 0.0));
+
+        // The magnitude (0.42) is out of the circle and kept as is
+        let radial = deadzone(DeadzoneShape::Radial, 0.2, 1.0, 0.0);
+        assert_near(radial.apply(diagonal.0, diagonal.1), diagonal);
+
+        // The magnitude is rescaled from 0.2..1 and the direction kept
+        let scaled = deadzone(DeadzoneShape::ScaledRadial, 0.2, 1.0, 0.0);
+        let magnitude = (0.18_f32.sqrt() - 0.2) / 0.8;
+        let component = magnitude / 2.0_f32.sqrt();
+        assert_near(scaled.apply(diagonal.0, diagonal.1), (component, component));
+    }
+
+    #[test]
+    fn deadzone_inner_and_outer_cutoffs() {
+        for shape in [
+            DeadzoneShape::Axial,
+            DeadzoneShape::Radial,
+            DeadzoneShape::ScaledRadial,
+        ] {
+            let settings = deadzone(shape, 0.2, 0.8, 0.0);
+
+            assert_near(settings.apply(0.1, 0.1), (0.0, 0.0));
+            assert_near(settings.apply(0.0, 0.0), (0.0, 0.0));
+            assert_near(settings.apply(0.9, 0.0), (1.0, 0.0));
+            assert_near(settings.apply(0.0, -0.8), (0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn anti_deadzone_starts_the_output_above_zero() {
+        let settings = deadzone(DeadzoneShape::ScaledRadial, 0.2, 1.0, 0.3);
+
+        assert_near(settings.apply(0.19, 0.0), (0.0, 0.0));
+        assert_near(settings.apply(0.2, 0.0), (0.3, 0.0));
+        assert_near(settings.apply(0.6, 0.0), (0.65, 0.0));
+        assert_near(settings.apply(1.0, 0.0), (1.0, 0.0));
+
+        let radial = deadzone(DeadzoneShape::Radial, 0.2, 1.0, 0.3);
+        assert_near(radial.apply(0.25, 0.0), (0.3, 0.0));
+    }
+
+    #[test]
+    fn deadzone_rescales_to_the_full_range() {
+        let settings = DeadzoneSettings::with_inner(0.1);
+
+        assert_near(settings.apply(1.0, 0.0), (1.0, 0.0));
+        assert_near(settings.apply(-1.0, 0.0), (-1.0, 0.0));
+        assert_near(settings.apply(0.0, 0.55), (0.0, 0.5));
+        assert_near(settings.apply(-0.55, 0.0), (-0.5, 0.0));
+    }
+
+    #[test]
+    fn custom_curve_points_are_sorted_when_read() {
+        let curve: ResponseCurve = serde_json::from_str(
+            r#"{"type": "custom", "points": [{"x": 1.0, "y": 1.0}, {"x": 0.0, "y": 0.0}, {"x": 0.5, "y": 0.2}]}"#,
+        )
+        .unwrap();
+
+        let ResponseCurve::Custom { points } = &curve else {
+            panic!("not a custom curve");
+        };
+        let xs: Vec<f32> = points.iter().map(|point| point.x).collect();
+        assert_eq!(xs, vec![0.0, 0.5, 1.0]);
+
+        assert!((curve.apply(0.25) - 0.1).abs() < 1e-6);
+        assert!((curve.apply(0.75) - 0.6).abs() < 1e-6);
+        assert!((curve.apply(-0.75) + 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn custom_curve_points_are_clamped() {
+        let curve: ResponseCurve = serde_json::from_str(
+            r#"{"type": "custom", "points": [{"x": -1.0, "y": -0.5}, {"x": 2.0, "y": 3.0}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            curve,
+            ResponseCurve::Custom {
+                points: vec![CurvePoint { x: 0.0, y: 0.0 }, CurvePoint { x: 1.0, y: 1.0 }]
+            }
+        );
+    }
+
+    #[test]
+    fn curves_keep_the_ends_and_the_sign() {
+        let curves = [
+            ResponseCurve::Linear,
+            ResponseCurve::Power { exponent: 2.0 },
+            ResponseCurve::SCurve { strength: 1.0 },
+            ResponseCurve::Custom { points: vec![] },
+        ];
+
+        for curve in curves {
+            assert_eq!(curve.apply(0.0), 0.0);
+            assert_eq!(curve.apply(1.0), 1.0);
+            assert_eq!(curve.apply(-1.0), -1.0);
+        }
+
+        assert_eq!(ResponseCurve::Power { exponent: 2.0 }.apply(-0.5), -0.25);
+    }
 }
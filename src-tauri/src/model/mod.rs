@@ -1,3 +1,5 @@
+pub mod calibration;
 pub mod config;
 pub mod gamepad;
 pub mod mapping;
+pub mod sdl_mapping;
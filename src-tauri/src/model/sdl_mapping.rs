@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::gamepad::HatPosition;
+
+/// Axis movement needed before a raw axis is recorded by the mapping wizard.
+pub const WIZARD_AXIS_THRESHOLD: i16 = i16::MAX / 2;
+
+/// Input read straight from the joystick, before SDL applies a gamepad mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/sdl-mapping.ts")]
+pub enum RawInput {
+    Button { index: u8, pressed: bool },
+    Axis { index: u8, value: i16 },
+    Hat { index: u8, position: HatPosition },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawInputEvent {
+    pub gamepad_id: u32,
+    pub input: RawInput,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdlElementKind {
+    Button,
+    /// Recorded while pushed right or down
+    Stick,
+    /// Recorded by its travel away from its rest position
+    Trigger,
+}
+
+/// Gamepad element of an SDL mapping string, in the order the wizard asks for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdlElement {
+    pub name: &'static str,
+    pub kind: SdlElementKind,
+}
+
+const fn element(name: &'static str, kind: SdlElementKind) -> SdlElement {
+    SdlElement { name, kind }
+}
+
+pub const SDL_ELEMENTS: [SdlElement; 32] = [
+    element("a", SdlElementKind::Button),
+    element("b", SdlElementKind::Button),
+    element("x", SdlElementKind::Button),
+    element("y", SdlElementKind::Button),
+    element("back", SdlElementKind::Button),
+    element("guide", SdlElementKind::Button),
+    element("start", SdlElementKind::Button),
+    element("leftstick", SdlElementKind::Button),
+    element("rightstick", SdlElementKind::Button),
+    element("leftshoulder", SdlElementKind::Button),
+    element("rightshoulder", SdlElementKind::Button),
+    element("dpup", SdlElementKind::Button),
+    element("dpdown", SdlElementKind::Button),
+    element("dpleft", SdlElementKind::Button),
+    element("dpright", SdlElementKind::Button),
+    element("misc1", SdlElementKind::Button),
+    element("misc2", SdlElementKind::Button),
+    element("misc3", SdlElementKind::Button),
+    element("misc4", SdlElementKind::Button),
+    element("misc5", SdlElementKind::Button),
+    element("misc6", SdlElementKind::Button),
+    element("paddle1", SdlElementKind::Button),
+    element("paddle2", SdlElementKind::Button),
+    element("paddle3", SdlElementKind::Button),
+    element("paddle4", SdlElementKind::Button),
+    element("touchpad", SdlElementKind::Button),
+    element("leftx", SdlElementKind::Stick),
+    element("lefty", SdlElementKind::Stick),
+    element("rightx", SdlElementKind::Stick),
+    element("righty", SdlElementKind::Stick),
+    element("lefttrigger", SdlElementKind::Trigger),
+    element("righttrigger", SdlElementKind::Trigger),
+];
+
+/// Progress of the "map this controller" wizard, streamed to the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[ts(export, export_to = "../../src/ts/bindings/sdl-mapping.ts")]
+pub enum MappingWizardUpdate {
+    /// The user should press or push `element`, it is skipped if nothing happens in time
+    Prompt {
+        element: String,
+        step: usize,
+        total: usize,
+    },
+    Recorded {
+        element: String,
+        binding: Option<String>,
+    },
+    Done {
+        mapping: String,
+    },
+}
+
+/// SDL binding (`b3`, `a1~`, `h0.4`...) for a raw input, `None` if the input isn't strong enough to be intentional.
+/// `axis_rest` is the value the axis had when first seen, triggers may rest at either end of their range.
+pub fn raw_input_binding(input: &RawInput, kind: SdlElementKind, axis_rest: i16) -> Option<String> {
+    match *input {
+        RawInput::Button { index, pressed } => pressed.then(|| format!("b{}", index)),
+        RawInput::Axis { index, value } if kind == SdlElementKind::Trigger => {
+            let travel = value as i32 - axis_rest as i32;
+
+            if travel.abs() < WIZARD_AXIS_THRESHOLD as i32 {
+                return None;
+            }
+
+            // Resting at one end the whole axis is the trigger, resting in the middle only the half it moved to
+            let binding = match (axis_rest, travel > 0) {
+                (rest, true) if rest <= -WIZARD_AXIS_THRESHOLD => format!("a{}", index),
+                (rest, false) if rest >= WIZARD_AXIS_THRESHOLD => format!("a{}~", index),
+                (rest, _) if rest.unsigned_abs() >= WIZARD_AXIS_THRESHOLD as u16 => return None,
+                (_, true) => format!("+a{}", index),
+                (_, false) => format!("-a{}", index),
+            };
+
+            Some(binding)
+        }
+        RawInput::Axis { index, value } => {
+            if value.unsigned_abs() < WIZARD_AXIS_THRESHOLD as u16 {
+                return None;
+            }
+
+            let binding = match (kind, value > 0) {
+                (SdlElementKind::Button, true) => format!("+a{}", index),
+                (SdlElementKind::Button, false) => format!("-a{}", index),
+                (_, true) => format!("a{}", index),
+                (_, false) => format!("a{}~", index),
+            };
+
+            Some(binding)
+        }
+        RawInput::Hat { index, position } => {
+            let mask = match position {
+                HatPosition::Up => 1,
+                HatPosition::Right => 2,
+                HatPosition::Down => 4,
+                HatPosition::Left => 8,
+                _ => return None,
+            };
+
+            Some(format!("h{}.{}", index, mask))
+        }
+    }
+}
+
+/// Builds a mapping line in the gamecontrollerdb format, unbound elements are left out.
+pub fn build_mapping_string(guid: &str, name: &str, bindings: &[(&str, String)]) -> String {
+    let mut mapping = format!("{},{},", guid, name.replace(',', " "));
+
+    for (element, binding) in bindings {
+        mapping.push_str(&format!("{}:{},", element, binding));
+    }
+
+    mapping.push_str(&format!("platform:{},", sdl_platform()));
+
+    mapping
+}
+
+/// GUID a mapping line applies to, used to replace an older mapping of the same controller.
+pub fn mapping_guid(mapping: &str) -> &str {
+    mapping.split(',').next().unwrap_or_default().trim()
+}
+
+/// Keeps the mapping lines of a gamecontrollerdb file that apply to the current platform.
+pub fn parse_gamecontrollerdb(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter(|line| line.split(',').count() > 2)
+        .filter(|line| match mapping_platform(line) {
+            Some(platform) => platform == sdl_platform(),
+            None => true,
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+fn mapping_platform(mapping: &str) -> Option<&str> {
+    mapping
+        .split(',')
+        .find_map(|field| field.trim().strip_prefix("platform:"))
+}
+
+/// Platform name as written in gamecontrollerdb files.
+fn sdl_platform() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "Windows"
+    } else if cfg!(target_os = "macos") {
+        "Mac OS X"
+    } else {
+        "Linux"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axis(index: u8, value: i16) -> RawInput {
+        RawInput::Axis { index, value }
+    }
+
+    #[test]
+    fn buttons_and_hats_are_bound_when_pressed() {
+        let button = |pressed| RawInput::Button { index: 3, pressed };
+        let hat = |position| RawInput::Hat { index: 0, position };
+
+        assert_eq!(
+            raw_input_binding(&button(true), SdlElementKind::Button, 0),
+            Some("b3".to_string())
+        );
+        assert_eq!(
+            raw_input_binding(&button(false), SdlElementKind::Button, 0),
+            None
+        );
+        assert_eq!(
+            raw_input_binding(&hat(HatPosition::Left), SdlElementKind::Button, 0),
+            Some("h0.8".to_string())
+        );
+        assert_eq!(
+            raw_input_binding(&hat(HatPosition::Centered), SdlElementKind::Button, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn axes_are_bound_past_the_threshold() {
+        assert_eq!(
+            raw_input_binding(&axis(1, 1000), SdlElementKind::Stick, 0),
+            None
+        );
+        assert_eq!(
+            raw_input_binding(&axis(1, 30000), SdlElementKind::Stick, 0),
+            Some("a1".to_string())
+        );
+        assert_eq!(
+            raw_input_binding(&axis(1, -30000), SdlElementKind::Stick, 0),
+            Some("a1~".to_string())
+        );
+        assert_eq!(
+            raw_input_binding(&axis(2, -30000), SdlElementKind::Button, 0),
+            Some("-a2".to_string())
+        );
+    }
+
+    #[test]
+    fn triggers_are_bound_by_their_travel_from_rest() {
+        // Resting at the bottom, the first samples of a press are still far negative
+        assert_eq!(
+            raw_input_binding(&axis(5, -30000), SdlElementKind::Trigger, i16::MIN),
+            None
+        );
+        assert_eq!(
+            raw_input_binding(&axis(5, 10000), SdlElementKind::Trigger, i16::MIN),
+            Some("a5".to_string())
+        );
+
+        assert_eq!(
+            raw_input_binding(&axis(5, -10000), SdlElementKind::Trigger, i16::MAX),
+            Some("a5~".to_string())
+        );
+        assert_eq!(
+            raw_input_binding(&axis(5, 30000), SdlElementKind::Trigger, 0),
+            Some("+a5".to_string())
+        );
+        assert_eq!(
+            raw_input_binding(&axis(5, -30000), SdlElementKind::Trigger, 0),
+            Some("-a5".to_string())
+        );
+    }
+
+    #[test]
+    fn mapping_string_lists_the_bindings() {
+        let mapping = build_mapping_string(
+            "0300abcd",
+            "Pad, Deluxe",
+            &[("a", "b0".to_string()), ("lefttrigger", "a5".to_string())],
+        );
+
+        assert_eq!(
+            mapping,
+            format!(
+                "0300abcd,Pad  Deluxe,a:b0,lefttrigger:a5,platform:{},",
+                sdl_platform()
+            )
+        );
+        assert_eq!(mapping_guid(&mapping), "0300abcd");
+        assert_eq!(mapping_guid(" 0300abcd ,Pad"), "0300abcd");
+    }
+
+    #[test]
+    fn gamecontrollerdb_keeps_this_platform() {
+        let other = match sdl_platform() {
+            "Linux" => "Windows",
+            _ => "Linux",
+        };
+        let content = format!(
+            "# Comment\n\n\
+             0300aaaa,Here,a:b0,platform:{platform},\n\
+             0300bbbb,Elsewhere,a:b0,platform:{other},\n\
+             0300cccc,Anywhere,a:b0,\n\
+             0300dddd,Truncated\n",
+            platform = sdl_platform(),
+        );
+
+        let guids: Vec<String> = parse_gamecontrollerdb(&content)
+            .iter()
+            .map(|mapping| mapping_guid(mapping).to_string())
+            .collect();
+
+        assert_eq!(guids, vec!["0300aaaa", "0300cccc"]);
+    }
+}
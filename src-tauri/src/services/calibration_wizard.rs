@@ -0,0 +1,267 @@
+use std::{collections::HashMap, error::Error, time::Duration};
+
+use tokio::sync::watch;
+
+use crate::{
+    model::{
+        calibration::{
+            AxisCalibration, CalibrationStep, CalibrationUpdate, ControllerCalibration,
+            StickCalibration,
+        },
+        gamepad::{GamepadAxis, GamepadState},
+    },
+    services::{config_service::ConfigService, gamepad_service::GAMEPAD_STATE},
+};
+
+/// Time the sticks and triggers are sampled at rest.
+const CENTER_DURATION: Duration = Duration::from_secs(2);
+/// Time given to roll the sticks along their edge and press the triggers all the way.
+const RANGE_DURATION: Duration = Duration::from_secs(8);
+/// Travel needed on one side of an axis before its recorded end is trusted, the full scale is kept otherwise.
+const MIN_RECORDED_TRAVEL: i32 = i16::MAX as i32 / 4;
+
+/// Records the center and range of every axis and the circularity of both sticks, then stores them on the known device.
+pub async fn run_calibration_wizard<F: Fn(CalibrationUpdate)>(
+    gamepad_id: u32,
+    on_update: F,
+) -> Result<ControllerCalibration, Box<dyn Error>> {
+    let (gamepad, mut gamepads_rx) = {
+        let state = GAMEPAD_STATE.read().unwrap();
+        let gamepad = state.get_gamepads().remove(&gamepad_id);
+        (gamepad, state.watch_gamepads())
+    };
+
+    let gamepad = gamepad.ok_or(format!("Gamepad {} is not connected", gamepad_id))?;
+
+    if gamepad.identity().guid.is_empty() {
+        return Err(format!("Gamepad {} has no GUID", gamepad_id).into());
+    }
+
+    let key = gamepad.identity().key();
+    let axes: Vec<GamepadAxis> = gamepad.axis().keys().copied().collect();
+
+    on_update(CalibrationUpdate::Prompt {
+        step: CalibrationStep::Center,
+        seconds: CENTER_DURATION.as_secs() as u32,
+    });
+
+    let mut sums: HashMap<GamepadAxis, (i64, i64)> = HashMap::new();
+
+    record_axes(&mut gamepads_rx, gamepad_id, CENTER_DURATION, |gamepad| {
+        for axis in &axes {
+            let (sum, count) = sums.entry(*axis).or_insert((0, 0));
+            *sum += gamepad.raw_axis_value(axis) as i64;
+            *count += 1;
+        }
+    })
+    .await?;
+
+    on_update(CalibrationUpdate::Prompt {
+        step: CalibrationStep::Range,
+        seconds: RANGE_DURATION.as_secs() as u32,
+    });
+
+    let mut ranges: HashMap<GamepadAxis, (i16, i16)> = HashMap::new();
+    let mut left_samples: Vec<(i16, i16)> = Vec::new();
+    let mut right_samples: Vec<(i16, i16)> = Vec::new();
+
+    record_axes(&mut gamepads_rx, gamepad_id, RANGE_DURATION, |gamepad| {
+        for axis in &axes {
+            let value = gamepad.raw_axis_value(axis);
+            let (min, max) = ranges.entry(*axis).or_insert((value, value));
+            *min = (*min).min(value);
+            *max = (*max).max(value);
+        }
+
+        left_samples.push((
+            gamepad.raw_axis_value(&GamepadAxis::LeftX),
+            gamepad.raw_axis_value(&GamepadAxis::LeftY),
+        ));
+        right_samples.push((
+            gamepad.raw_axis_value(&GamepadAxis::RightX),
+            gamepad.raw_axis_value(&GamepadAxis::RightY),
+        ));
+    })
+    .await?;
+
+    let mut calibration = ControllerCalibration::default();
+
+    for axis in &axes {
+        let Some((sum, count)) = sums.get(axis).filter(|(_, count)| *count > 0) else {
+            continue;
+        };
+
+        let center = (sum / count) as i16;
+        let (min, max) = ranges.get(axis).copied().unwrap_or((center, center));
+
+        calibration
+            .axes
+            .insert(*axis, axis_calibration(center, min, max));
+    }
+
+    calibration.left_stick = stick_calibration(
+        &calibration,
+        GamepadAxis::LeftX,
+        GamepadAxis::LeftY,
+        &left_samples,
+    );
+    calibration.right_stick = stick_calibration(
+        &calibration,
+        GamepadAxis::RightX,
+        GamepadAxis::RightY,
+        &right_samples,
+    );
+
+    ConfigService::get_instance().set_device_calibration(&key, Some(calibration.clone()))?;
+
+    on_update(CalibrationUpdate::Done {
+        calibration: calibration.clone(),
+        left_stick_error: calibration
+            .left_stick
+            .as_ref()
+            .map(StickCalibration::circularity_error),
+        right_stick_error: calibration
+            .right_stick
+            .as_ref()
+            .map(StickCalibration::circularity_error),
+    });
+
+    Ok(calibration)
+}
+
+/// Calls `record` with every state of the gamepad published during `duration`.
+async fn record_axes<F: FnMut(&GamepadState)>(
+    gamepads_rx: &mut watch::Receiver<HashMap<u32, GamepadState>>,
+    gamepad_id: u32,
+    duration: Duration,
+    mut record: F,
+) -> Result<(), Box<dyn Error>> {
+    let deadline = tokio::time::Instant::now() + duration;
+
+    loop {
+        {
+            let gamepads = gamepads_rx.borrow_and_update();
+            let gamepad = gamepads
+                .get(&gamepad_id)
+                .ok_or(format!("Gamepad {} disconnected", gamepad_id))?;
+
+            record(gamepad);
+        }
+
+        match tokio::time::timeout_at(deadline, gamepads_rx.changed()).await {
+            Ok(Ok(())) => continue,
+            Ok(Err(_)) => return Err("Gamepad monitor stopped".into()),
+            Err(_) => return Ok(()),
+        }
+    }
+}
+
+/// Keeps the full scale on a side the axis was barely moved to, a trigger only has a positive side.
+fn axis_calibration(center: i16, min: i16, max: i16) -> AxisCalibration {
+    let min = match center as i32 - min as i32 >= MIN_RECORDED_TRAVEL {
+        true => min,
+        false => -i16::MAX,
+    };
+
+    let max = match max as i32 - center as i32 >= MIN_RECORDED_TRAVEL {
+        true => max,
+        false => i16::MAX,
+    };
+
+    AxisCalibration { center, min, max }
+}
+
+fn stick_calibration(
+    calibration: &ControllerCalibration,
+    x_axis: GamepadAxis,
+    y_axis: GamepadAxis,
+    samples: &[(i16, i16)],
+) -> Option<StickCalibration> {
+    let x_calibration = calibration.axis(&x_axis)?;
+    let y_calibration = calibration.axis(&y_axis)?;
+
+    let samples: Vec<(f32, f32)> = samples
+        .iter()
+        .map(|(x, y)| {
+            (
+                x_calibration.apply(*x) as f32 / i16::MAX as f32,
+                y_calibration.apply(*y) as f32 / i16::MAX as f32,
+            )
+        })
+        .collect();
+
+    Some(StickCalibration::from_samples(&samples))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asymmetric_range_is_kept() {
+        assert_eq!(
+            axis_calibration(1000, -20000, 25000),
+            AxisCalibration {
+                center: 1000,
+                min: -20000,
+                max: 25000,
+            }
+        );
+    }
+
+    #[test]
+    fn one_sided_trigger_keeps_full_scale() {
+        // Never pushed below its rest position
+        let pressed = axis_calibration(0, 0, 30000);
+        assert_eq!(pressed.min, -i16::MAX);
+        assert_eq!(pressed.apply(30000), i16::MAX);
+        assert_eq!(pressed.apply(0), 0);
+
+        // Barely pressed during the recording, the side isn't trusted either
+        let barely_pressed = axis_calibration(0, 0, 4000);
+        assert_eq!(barely_pressed.max, i16::MAX);
+        assert_eq!(barely_pressed.apply(i16::MAX), i16::MAX);
+        assert_eq!(barely_pressed.apply(4000), 4000);
+    }
+
+    #[test]
+    fn stick_samples_are_calibrated_before_measuring_the_reach() {
+        let mut calibration = ControllerCalibration::default();
+        calibration
+            .axes
+            .insert(GamepadAxis::LeftX, axis_calibration(0, -16000, 16000));
+        calibration
+            .axes
+            .insert(GamepadAxis::LeftY, axis_calibration(0, -32000, 32000));
+
+        // Samples reaching the recorded range are on the unit circle once calibrated
+        let samples: Vec<(i16, i16)> = (0..360)
+            .map(|degree| (degree as f32).to_radians())
+            .map(|angle| {
+                (
+                    (angle.cos() * 16000.0) as i16,
+                    (angle.sin() * 32000.0) as i16,
+                )
+            })
+            .collect();
+
+        let stick = stick_calibration(
+            &calibration,
+            GamepadAxis::LeftX,
+            GamepadAxis::LeftY,
+            &samples,
+        )
+        .unwrap();
+        assert!(stick.circularity_error() < 0.01);
+
+        assert!(
+            stick_calibration(
+                &calibration,
+                GamepadAxis::RightX,
+                GamepadAxis::RightY,
+                &samples
+            )
+            .is_none()
+        );
+    }
+}
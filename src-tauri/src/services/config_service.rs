@@ -1,9 +1,21 @@
-use std::sync::{Arc, OnceLock};
+use std::{
+    error::Error,
+    fs,
+    sync::{Arc, Mutex, OnceLock},
+};
 use tauri::{AppHandle, Wry};
 use tauri_plugin_store::{Store, StoreExt};
 use tokio::sync::watch;
 
-use crate::{get_app_handle, model::config::Config};
+use crate::{
+    get_app_handle,
+    model::{
+        calibration::ControllerCalibration,
+        config::{Config, KnownDevice, Profile},
+        gamepad::{ControllerIdentity, LedColor},
+        sdl_mapping::{mapping_guid, parse_gamecontrollerdb},
+    },
+};
 
 const ROOT_KEY: &str = "config";
 
@@ -13,6 +25,8 @@ pub static CONFIG_SERVICE: OnceLock<ConfigService> = OnceLock::new();
 pub struct ConfigService {
     store: Arc<Store<Wry>>,
     tx: watch::Sender<Config>,
+    /// Serializes the read-modify-write of `set` and `update`, concurrent writes would lose one another
+    write_lock: Mutex<()>,
 }
 
 impl ConfigService {
@@ -22,16 +36,25 @@ impl ConfigService {
 
     pub fn new(app: AppHandle) -> Self {
         let store = app.store("config.cfg").unwrap();
-        let (tx, rx) = watch::channel(
-            store
-                .get(ROOT_KEY)
-                .map(|value| serde_json::from_value(value).unwrap_or(Config::default()))
-                .unwrap_or(Config::default()),
-        );
+        let mut config: Config = store
+            .get(ROOT_KEY)
+            .map(|value| serde_json::from_value(value).unwrap_or(Config::default()))
+            .unwrap_or(Config::default());
+
+        if config.migrate() {
+            store.set(ROOT_KEY, serde_json::to_value(&config).unwrap());
+            let _ = store.save();
+        }
+
+        let (tx, rx) = watch::channel(config);
 
         let _ = CONFIG_RECEIVER.set(rx);
 
-        Self { store, tx }
+        Self {
+            store,
+            tx,
+            write_lock: Mutex::new(()),
+        }
     }
 
     pub fn get_config(&self) -> Config {
@@ -47,6 +70,7 @@ impl ConfigService {
 
     pub fn set(&self, key: &str, value: serde_json::Value) {
         println!("set config AA: {:?} {:?}", key, value);
+        let _guard = self.write_lock.lock().unwrap();
         let mut config = self.get_config();
         config.set(key, value.clone());
         self.store
@@ -54,4 +78,262 @@ impl ConfigService {
         let _ = self.store.save();
         let _ = self.tx.send(self.get_config());
     }
+
+    /// Applies `update` to the stored config and returns its result, checks made inside it can't race other writes.
+    pub fn update<R, F: FnOnce(&mut Config) -> R>(&self, update: F) -> R {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut config = self.get_config();
+        let result = update(&mut config);
+        self.store
+            .set(ROOT_KEY, serde_json::to_value(config).unwrap());
+        let _ = self.store.save();
+        let _ = self.tx.send(self.get_config());
+        result
+    }
+
+    /// Adds the controller to the known devices, or refreshes its identity if it was already known.
+    pub fn remember_device(&self, identity: &ControllerIdentity) {
+        if identity.guid.is_empty() {
+            return;
+        }
+
+        let key = identity.key();
+
+        if let Some(device) = self.get_config().known_device(&key)
+            && device.identity == *identity
+        {
+            return;
+        }
+
+        self.update(|config| match config.known_device_mut(&key) {
+            Some(device) => device.identity = identity.clone(),
+            None => config
+                .known_devices
+                .push(KnownDevice::new(identity.clone())),
+        });
+    }
+
+    pub fn rename_device(&self, key: &str, alias: Option<String>) -> Result<(), String> {
+        if self.get_config().known_device(key).is_none() {
+            return Err(format!("Unknown device: {}", key));
+        }
+
+        self.update(|config| {
+            if let Some(device) = config.known_device_mut(key) {
+                device.alias = alias.filter(|alias| !alias.trim().is_empty());
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn set_device_led(&self, key: &str, color: Option<LedColor>) -> Result<(), String> {
+        if self.get_config().known_device(key).is_none() {
+            return Err(format!("Unknown device: {}", key));
+        }
+
+        self.update(|config| {
+            if let Some(device) = config.known_device_mut(key) {
+                device.led_color = color;
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn set_device_calibration(
+        &self,
+        key: &str,
+        calibration: Option<ControllerCalibration>,
+    ) -> Result<(), String> {
+        if self.get_config().known_device(key).is_none() {
+            return Err(format!("Unknown device: {}", key));
+        }
+
+        self.update(|config| {
+            if let Some(device) = config.known_device_mut(key) {
+                device.calibration = calibration;
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Stores SDL mapping lines, a mapping replaces the previous one for the same GUID. Returns how many were added.
+    pub fn add_sdl_mappings(&self, mappings: Vec<String>) -> usize {
+        let count = mappings.len();
+
+        self.update(|config| {
+            for mapping in mappings {
+                config
+                    .custom_sdl_mappings
+                    .retain(|existing| mapping_guid(existing) != mapping_guid(&mapping));
+                config.custom_sdl_mappings.push(mapping);
+            }
+        });
+
+        count
+    }
+
+    pub fn import_gamecontrollerdb(&self, path: &str) -> Result<usize, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read mapping database {}: {}", path, e))?;
+
+        let mappings = parse_gamecontrollerdb(&content);
+
+        if mappings.is_empty() {
+            return Err(format!("No mapping for this platform found in {}", path).into());
+        }
+
+        Ok(self.add_sdl_mappings(mappings))
+    }
+
+    pub fn remove_sdl_mapping(&self, guid: &str) {
+        self.update(|config| {
+            config
+                .custom_sdl_mappings
+                .retain(|mapping| mapping_guid(mapping) != guid)
+        });
+    }
+
+    pub fn forget_device(&self, key: &str) {
+        self.update(|config| config.known_devices.retain(|device| device.key != key));
+    }
+
+    /// Config with the default profile in place, the profile operations work on it.
+    fn profiles_config(&self) -> Config {
+        let mut config = self.get_config();
+        config.ensure_profiles();
+        config
+    }
+
+    /// Adds an empty profile and returns its id.
+    pub fn create_profile(&self, name: &str) -> Result<String, String> {
+        let name = profile_name(name)?;
+
+        let id = self.update(|config| {
+            config.ensure_profiles();
+            let id = config.new_profile_id();
+            config.profiles.push(Profile {
+                id: id.clone(),
+                name,
+                mappings: vec![],
+                layers: vec![],
+                led_color: None,
+            });
+            id
+        });
+
+        Ok(id)
+    }
+
+    /// Copies the mappings and layers of a profile into a new one and returns its id.
+    pub fn clone_profile(&self, id: &str, name: &str) -> Result<String, String> {
+        let name = profile_name(name)?;
+
+        self.update(|config| {
+            config.ensure_profiles();
+
+            // The active profile's mappings are the working copy
+            let mut current = config.clone();
+            current.save_active_profile();
+
+            let source = current
+                .profile(id)
+                .cloned()
+                .ok_or(format!("Unknown profile: {}", id))?;
+            let clone_id = config.new_profile_id();
+
+            config.profiles.push(Profile {
+                id: clone_id.clone(),
+                name,
+                mappings: source.mappings,
+                layers: source.layers,
+                led_color: source.led_color,
+            });
+
+            Ok(clone_id)
+        })
+    }
+
+    pub fn rename_profile(&self, id: &str, name: &str) -> Result<(), String> {
+        let name = profile_name(name)?;
+
+        if self.profiles_config().profile(id).is_none() {
+            return Err(format!("Unknown profile: {}", id));
+        }
+
+        self.update(|config| {
+            config.ensure_profiles();
+            if let Some(profile) = config.profile_mut(id) {
+                profile.name = name;
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn set_profile_led(&self, id: &str, color: Option<LedColor>) -> Result<(), String> {
+        if self.profiles_config().profile(id).is_none() {
+            return Err(format!("Unknown profile: {}", id));
+        }
+
+        self.update(|config| {
+            config.ensure_profiles();
+            if let Some(profile) = config.profile_mut(id) {
+                profile.led_color = color;
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Removes a profile, the next one is activated when it was active. The last profile can't be deleted.
+    pub fn delete_profile(&self, id: &str) -> Result<(), String> {
+        self.update(|config| {
+            config.ensure_profiles();
+
+            if config.profile(id).is_none() {
+                return Err(format!("Unknown profile: {}", id));
+            }
+
+            if config.profiles.len() <= 1 {
+                return Err("The last profile can't be deleted".to_string());
+            }
+
+            if config.active_profile.as_deref() == Some(id) {
+                let next = config.adjacent_profile(1);
+                let _ = config.activate_profile(&next);
+            }
+
+            config.profiles.retain(|profile| profile.id != id);
+
+            Ok(())
+        })
+    }
+
+    pub fn activate_profile(&self, id: &str) -> Result<(), String> {
+        if self.profiles_config().profile(id).is_none() {
+            return Err(format!("Unknown profile: {}", id));
+        }
+
+        self.update(|config| {
+            let _ = config.activate_profile(id);
+        });
+
+        Ok(())
+    }
+
+    /// Activates the profile `offset` places away from the active one.
+    pub fn cycle_profile(&self, offset: isize) {
+        let id = self.profiles_config().adjacent_profile(offset);
+        let _ = self.activate_profile(&id);
+    }
+}
+
+fn profile_name(name: &str) -> Result<String, String> {
+    match name.trim() {
+        "" => Err("A profile needs a name".to_string()),
+        name => Ok(name.to_string()),
+    }
 }
@@ -1,32 +1,59 @@
-use sdl3::{
-    EventPump, GamepadSubsystem, Sdl,
-    event::Event,
-    gamepad::{Axis, Button, Gamepad},
-};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     error::Error,
-    sync::{Arc, LazyLock, OnceLock, RwLock},
+    sync::{Arc, LazyLock, OnceLock, RwLock, mpsc},
+    time::{Duration, Instant},
+};
+use tokio::sync::{broadcast, mpsc::UnboundedReceiver, mpsc::UnboundedSender, watch};
+
+use crate::{
+    model::{
+        calibration::ControllerCalibration,
+        config::Config,
+        gamepad::{GamepadState, InputEvent, InputEventKind, LowBatteryWarning},
+        sdl_mapping::RawInputEvent,
+    },
+    services::{
+        config_service::CONFIG_SERVICE,
+        input_backend::{self, BackendCommand, BackendEvent, InputBackend},
+    },
 };
-use tokio::sync::watch;
-
-use crate::model::gamepad::{GamepadAxis, GamepadButton, GamepadState};
 
 static GAMEPAD_STATE_RECEIVER: OnceLock<watch::Receiver<HashMap<u32, GamepadState>>> =
     OnceLock::new();
 
+/// Command queue of the latest monitor started.
+static BACKEND_COMMANDS: RwLock<Option<mpsc::Sender<BackendCommand>>> = RwLock::new(None);
+
+/// How long the monitor waits for an event before handling pending commands.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Sensors report hundreds of samples per second, the watchers are woken at most this often for them.
+const SENSOR_PUBLISH_INTERVAL: Duration = Duration::from_millis(10);
+
 pub static GAMEPAD_STATE: LazyLock<Arc<RwLock<GamepadsState>>> =
     LazyLock::new(|| Arc::new(RwLock::new(GamepadsState::new())));
 
 pub fn start_gamepad_monitor() -> Result<(), Box<dyn Error>> {
-    let mut monitor = GamepadsMonitor::new()?;
+    let mut monitor = GamepadsMonitor::new(input_backend::backend_from_env()?);
     monitor.start()?;
     Ok(())
 }
 
+/// Queues a command for the running input backend, ignored if the monitor isn't started.
+pub fn send_backend_command(command: BackendCommand) {
+    if let Some(sender) = BACKEND_COMMANDS.read().unwrap().as_ref() {
+        let _ = sender.send(command);
+    }
+}
+
 pub struct GamepadsState {
     gamepads: HashMap<u32, GamepadState>,
     gamepads_tx: watch::Sender<HashMap<u32, GamepadState>>,
+    low_battery_tx: broadcast::Sender<LowBatteryWarning>,
+    raw_input_tx: broadcast::Sender<RawInputEvent>,
+    input_events_tx: broadcast::Sender<InputEvent>,
+    input_queues: Vec<UnboundedSender<InputEvent>>,
 }
 
 impl GamepadsState {
@@ -36,9 +63,17 @@ impl GamepadsState {
 
         let _ = GAMEPAD_STATE_RECEIVER.set(rx);
 
+        let (low_battery_tx, _) = broadcast::channel(8);
+        let (raw_input_tx, _) = broadcast::channel(64);
+        let (input_events_tx, _) = broadcast::channel(1024);
+
         Self {
             gamepads,
             gamepads_tx: tx,
+            low_battery_tx,
+            raw_input_tx,
+            input_events_tx,
+            input_queues: Vec::new(),
         }
     }
 
@@ -46,157 +81,625 @@ impl GamepadsState {
         let _ = self.gamepads_tx.send(gamepads);
     }
 
+    /// Applies `update` to the gamepad and to the published snapshot in place, without cloning the whole map.
+    fn update_gamepad<F: Fn(&mut GamepadState)>(&mut self, id: u32, update: F) -> bool {
+        self.update_gamepad_notifying(id, update, true)
+    }
+
+    /// Like `update_gamepad`, the watchers only see the change once `notify` is set or another update wakes them.
+    fn update_gamepad_notifying<F: Fn(&mut GamepadState)>(
+        &mut self,
+        id: u32,
+        update: F,
+        notify: bool,
+    ) -> bool {
+        let Some(gamepad) = self.gamepads.get_mut(&id) else {
+            return false;
+        };
+
+        update(gamepad);
+
+        self.gamepads_tx
+            .send_if_modified(|gamepads| match gamepads.get_mut(&id) {
+                Some(gamepad) => {
+                    update(gamepad);
+                    notify
+                }
+                None => false,
+            });
+
+        true
+    }
+
+    fn publish_input_event(&mut self, gamepad_id: u32, kind: InputEventKind) {
+        let event = InputEvent::new(gamepad_id, kind, Instant::now());
+
+        let _ = self.input_events_tx.send(event);
+        self.input_queues.retain(|queue| queue.send(event).is_ok());
+    }
+
     pub fn watch_gamepads(&self) -> watch::Receiver<HashMap<u32, GamepadState>> {
         self.gamepads_tx.subscribe()
     }
 
+    pub fn subscribe_low_battery(&self) -> broadcast::Receiver<LowBatteryWarning> {
+        self.low_battery_tx.subscribe()
+    }
+
+    /// Button and axis changes of every gamepad, in the order they happened.
+    pub fn subscribe_input_events(&self) -> broadcast::Receiver<InputEvent> {
+        self.input_events_tx.subscribe()
+    }
+
+    /// Unbounded queue of the input events, unlike `subscribe_input_events` it never drops an event when lagging behind.
+    pub fn subscribe_input_queue(&mut self) -> UnboundedReceiver<InputEvent> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.input_queues.push(tx);
+        rx
+    }
+
+    /// Raw joystick input of the devices with raw capture enabled.
+    pub fn subscribe_raw_input(&self) -> broadcast::Receiver<RawInputEvent> {
+        self.raw_input_tx.subscribe()
+    }
+
     pub fn get_gamepads(&self) -> HashMap<u32, GamepadState> {
         self.gamepads.clone()
     }
+
+    /// Lowest player slot (starting at 1) not used by a connected gamepad.
+    fn free_player_slot(&self) -> u8 {
+        (1..=u8::MAX)
+            .find(|slot| {
+                !self
+                    .gamepads
+                    .values()
+                    .any(|gamepad| gamepad.player_slot() == *slot)
+            })
+            .unwrap_or(u8::MAX)
+    }
 }
 struct GamepadsMonitor {
-    sdl_context: Sdl,
-    controller_subsystem: GamepadSubsystem,
-    gamepads: HashMap<u32, Gamepad>,
+    backend: Box<dyn InputBackend>,
+    commands: mpsc::Receiver<BackendCommand>,
+    low_battery_warned: HashSet<u32>,
+    config_rx: Option<watch::Receiver<Config>>,
+    /// SDL can't forget a mapping, removed ones stay active until restart
+    applied_sdl_mappings: Vec<String>,
+    last_sensor_publish: Option<Instant>,
 }
 
 impl GamepadsMonitor {
-    pub fn new() -> Result<Self, Box<dyn Error>> {
-        let sdl_context: Sdl = sdl3::init()?;
-        let controller_subsystem = sdl_context.gamepad()?;
+    pub fn new(backend: Box<dyn InputBackend>) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        *BACKEND_COMMANDS.write().unwrap() = Some(tx);
 
-        Ok(Self {
-            sdl_context,
-            controller_subsystem,
-            gamepads: HashMap::new(),
-        })
+        Self {
+            backend,
+            commands: rx,
+            low_battery_warned: HashSet::new(),
+            config_rx: CONFIG_SERVICE.get().map(|config| config.watch()),
+            applied_sdl_mappings: Vec::new(),
+            last_sensor_publish: None,
+        }
     }
 
     pub fn start(&mut self) -> Result<(), Box<dyn Error>> {
-        println!("Starting gamepad monitor");
+        println!("Starting gamepad monitor ({} backend)", self.backend.name());
 
-        self.controller_subsystem.set_events_processing_state(true);
-
-        let mut event_pump = self
-            .sdl_context
-            .event_pump()
-            .map_err(|e| format!("Failed to create event pump: {}", e))?;
+        if let Some(config_service) = CONFIG_SERVICE.get() {
+            self.apply_sdl_mappings(config_service.get_config().custom_sdl_mappings);
+        }
 
-        self.update_events(&mut event_pump)?;
+        while self.poll()? {}
 
         Ok(())
     }
 
-    fn update_events(&mut self, event_pump: &mut EventPump) -> Result<(), Box<dyn Error>> {
-        for event in event_pump.wait_iter() {
-            let mut state = { GAMEPAD_STATE.write().unwrap() };
+    /// Sends the pending commands and handles the next event, returns false once the backend is done.
+    fn poll(&mut self) -> Result<bool, Box<dyn Error>> {
+        if self.backend.is_exhausted() {
+            return Ok(false);
+        }
+
+        self.reload_config();
+        self.send_pending_commands();
+
+        match self.backend.next_event(COMMAND_POLL_INTERVAL)? {
+            Some(BackendEvent::Quit) => return Ok(false),
+            Some(event) => self.handle_event(event),
+            None => {}
+        }
+
+        Ok(true)
+    }
+
+    fn send_pending_commands(&mut self) {
+        while let Ok(command) = self.commands.try_recv() {
+            if let Err(e) = self.backend.send_command(&command) {
+                eprintln!("Error sending {:?}: {}", command, e);
+            }
+        }
+    }
+
+    fn reload_config(&mut self) {
+        let Some(config_rx) = self.config_rx.as_mut() else {
+            return;
+        };
 
-            match event {
-                Event::Quit { .. } => {
-                    break;
+        if !config_rx.has_changed().unwrap_or(false) {
+            return;
+        }
+
+        let config = config_rx.borrow_and_update().clone();
+        self.apply_sdl_mappings(config.custom_sdl_mappings.clone());
+        self.apply_calibrations(&config);
+    }
+
+    /// Gives every connected gamepad the calibration stored on its known device.
+    fn apply_calibrations(&self, config: &Config) {
+        let mut state = { GAMEPAD_STATE.write().unwrap() };
+
+        let changed: Vec<(u32, Option<ControllerCalibration>)> = state
+            .gamepads
+            .values()
+            .filter_map(|gamepad| {
+                let calibration = device_calibration(config, gamepad);
+                (gamepad.calibration() != calibration.as_ref())
+                    .then_some((gamepad.id(), calibration))
+            })
+            .collect();
+
+        for (id, calibration) in changed {
+            state.update_gamepad(id, |gamepad| gamepad.set_calibration(calibration.clone()));
+        }
+    }
+
+    fn apply_sdl_mappings(&mut self, mappings: Vec<String>) {
+        let new_mappings: Vec<String> = mappings
+            .into_iter()
+            .filter(|mapping| !self.applied_sdl_mappings.contains(mapping))
+            .collect();
+
+        if new_mappings.is_empty() {
+            return;
+        }
+
+        let command = BackendCommand::AddSdlMappings {
+            mappings: new_mappings.clone(),
+        };
+
+        if let Err(e) = self.backend.send_command(&command) {
+            eprintln!("Error applying SDL mappings: {}", e);
+        }
+
+        self.applied_sdl_mappings.extend(new_mappings);
+    }
+
+    fn handle_event(&mut self, mut event: BackendEvent) {
+        // Remembering the device saves the config, done before taking the state lock
+        if let BackendEvent::DeviceAdded(gamepad) = &mut event
+            && let Some(config_service) = CONFIG_SERVICE.get()
+        {
+            config_service.remember_device(gamepad.identity());
+            gamepad.set_calibration(device_calibration(&config_service.get_config(), gamepad));
+        }
+
+        let mut state = { GAMEPAD_STATE.write().unwrap() };
+
+        match event {
+            BackendEvent::Button {
+                id,
+                button,
+                pressed,
+            } => {
+                let was_pressed = match state.gamepads.get(&id) {
+                    Some(gamepad) => gamepad.is_button_pressed(&button),
+                    None => return,
+                };
+
+                state.update_gamepad(id, |gamepad| gamepad.set_button(button, pressed));
+
+                if was_pressed != pressed {
+                    let kind = match pressed {
+                        true => InputEventKind::ButtonDown { button },
+                        false => InputEventKind::ButtonUp { button },
+                    };
+
+                    state.publish_input_event(id, kind);
                 }
-                Event::ControllerButtonDown { which, button, .. }
-                | Event::ControllerButtonUp { which, button, .. } => {
-                    let pressed = matches!(event, Event::ControllerButtonDown { .. });
-
-                    if let Some(gamepad) = state.gamepads.get_mut(&which) {
-                        gamepad.set_button(sdl_button_to_gamepad_button(&button), pressed);
-                        state.broadcast(state.gamepads.clone());
-                    }
+            }
+            BackendEvent::Axis { id, axis, value } => {
+                let previous = match state.gamepads.get(&id) {
+                    Some(gamepad) => gamepad.axis_value(&axis),
+                    None => return,
+                };
+
+                state.update_gamepad(id, |gamepad| gamepad.set_axis(axis, value));
+
+                let value = state.gamepads[&id].axis_value(&axis);
+
+                if previous != value {
+                    state.publish_input_event(id, InputEventKind::AxisChanged { axis, value });
                 }
-                Event::ControllerAxisMotion {
-                    timestamp: _,
-                    which,
-                    axis,
-                    value,
-                } => {
-                    if let Some(gamepad) = state.gamepads.get_mut(&which) {
-                        gamepad.set_axis(sdl_axis_to_gamepad_axis(&axis), value);
-                        state.broadcast(state.gamepads.clone());
-                    }
+            }
+            BackendEvent::Hat { id, hat, position } => {
+                state.update_gamepad(id, |gamepad| gamepad.set_hat(hat, position));
+            }
+            BackendEvent::Sensor {
+                id,
+                sensor,
+                reading,
+            } => {
+                let now = Instant::now();
+                let notify = self
+                    .last_sensor_publish
+                    .is_none_or(|last| now.duration_since(last) >= SENSOR_PUBLISH_INTERVAL);
+
+                if notify {
+                    self.last_sensor_publish = Some(now);
                 }
-                Event::ControllerDeviceAdded { .. } => {
-                    let res = self.add_device();
-                    if let Ok(gamepad) = &res {
-                        println!("Gamepad added: {}", gamepad.id());
-                        state.gamepads.insert(gamepad.id(), gamepad.clone());
-                        state.broadcast(state.gamepads.clone());
-                    }
+
+                state.update_gamepad_notifying(
+                    id,
+                    |gamepad| gamepad.set_sensor_reading(sensor, reading),
+                    notify,
+                );
+            }
+            BackendEvent::Touchpad {
+                id,
+                finger,
+                pressed,
+            } => {
+                state.update_gamepad(id, |gamepad| gamepad.set_touchpad_finger(finger, pressed));
+            }
+            BackendEvent::Power { id, power } => {
+                if state.update_gamepad(id, |gamepad| gamepad.set_power(power)) {
+                    self.check_low_battery(&state, id);
                 }
-                Event::ControllerDeviceRemoved { which, .. } => {
-                    state.gamepads.remove(&which);
+            }
+            BackendEvent::DeviceAdded(mut gamepad) => {
+                // Re-added under the same id (a joystick that got a gamepad mapping) keeps its slot
+                let player_slot = match state.gamepads.get(&gamepad.id()) {
+                    Some(previous) => previous.player_slot(),
+                    None => state.free_player_slot(),
+                };
+
+                gamepad.set_player_slot(player_slot);
+
+                println!(
+                    "Gamepad added: {} ({})",
+                    gamepad.id(),
+                    gamepad.identity().key()
+                );
+
+                let id = gamepad.id();
+                state.gamepads.insert(id, *gamepad);
+                state.broadcast(state.gamepads.clone());
+
+                // Plugged in already low, no power event may follow
+                self.check_low_battery(&state, id);
+            }
+            BackendEvent::DeviceRemoved { id } => {
+                self.low_battery_warned.remove(&id);
+
+                if state.gamepads.remove(&id).is_some() {
+                    println!("Gamepad removed: {}", id);
                     state.broadcast(state.gamepads.clone());
                 }
-                _ => {}
             }
+            BackendEvent::RawInput { id, input } => {
+                let _ = state.raw_input_tx.send(RawInputEvent {
+                    gamepad_id: id,
+                    input,
+                });
+            }
+            BackendEvent::Quit => {}
+        }
+    }
+    /// Warns once when a gamepad drops under the low battery threshold, again only after it recovered.
+    fn check_low_battery(&mut self, state: &GamepadsState, id: u32) {
+        let gamepad = match state.gamepads.get(&id) {
+            Some(gamepad) => gamepad,
+            None => return,
+        };
+
+        let threshold = CONFIG_SERVICE
+            .get()
+            .map(|config| config.get_config().low_battery_threshold)
+            .unwrap_or(15);
+
+        if !gamepad.power().is_low(threshold) {
+            self.low_battery_warned.remove(&id);
+            return;
         }
 
-        Ok(())
+        if !self.low_battery_warned.insert(id) {
+            return;
+        }
+
+        let _ = state.low_battery_tx.send(LowBatteryWarning {
+            gamepad_id: id,
+            name: gamepad.name().to_string(),
+            battery_percent: gamepad.power().battery_percent.unwrap_or(0),
+        });
     }
+}
 
-    fn add_device(&mut self) -> Result<GamepadState, Box<dyn Error>> {
-        let pad_ids = self.controller_subsystem.gamepads()?;
+fn device_calibration(config: &Config, gamepad: &GamepadState) -> Option<ControllerCalibration> {
+    config
+        .known_device(&gamepad.identity().key())
+        .and_then(|device| device.calibration.clone())
+}
 
-        for id in pad_ids {
-            if !self.controller_subsystem.is_gamepad(id) {
-                continue;
-            }
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        model::{
+            gamepad::{BatteryStatus, GamepadButton, MotionSensor, PowerState, SensorReading},
+            mapping::{Action, ButtonMapping, Mapping, Rumble, button_mapping},
+        },
+        services::{
+            input_sink::{RecordingSink, SinkEvent},
+            mapping_service::MappingExecutor,
+            scripted_backend::{ScriptStep, ScriptedBackend, ScriptedEvent},
+        },
+    };
+
+    /// The tests share `GAMEPAD_STATE` and the backend command queue.
+    static GLOBALS: Mutex<()> = Mutex::new(());
+
+    /// Monitor replaying a script, with a mapping executor reading `GAMEPAD_STATE` on the other side.
+    struct Pipeline {
+        monitor: GamepadsMonitor,
+        executor: MappingExecutor,
+        sink: RecordingSink,
+        gamepads: watch::Receiver<HashMap<u32, GamepadState>>,
+        input_queue: UnboundedReceiver<InputEvent>,
+        sent_commands: Arc<Mutex<Vec<BackendCommand>>>,
+    }
 
-            let gamepadcontroller = match self.controller_subsystem.open(id) {
-                Ok(controller) => controller,
-                Err(_) => continue,
+    impl Pipeline {
+        fn new(backend: ScriptedBackend, mappings: Vec<Mapping>) -> Self {
+            let sent_commands = backend.sent_commands();
+
+            let (gamepads, input_queue) = {
+                let mut state = GAMEPAD_STATE.write().unwrap();
+                state.gamepads.clear();
+                state.broadcast(HashMap::new());
+                (state.watch_gamepads(), state.subscribe_input_queue())
             };
 
-            if !gamepadcontroller.connected() {
-                continue;
+            let sink = RecordingSink::default();
+            let mut executor = MappingExecutor::with_sink(Box::new(sink.clone()));
+            executor.set_config(Config {
+                mappings,
+                ..Config::default()
+            });
+
+            Self {
+                monitor: GamepadsMonitor::new(Box::new(backend)),
+                executor,
+                sink,
+                gamepads,
+                input_queue,
+                sent_commands,
             }
+        }
 
-            let gamepad = GamepadState::from_sdl_gamepad(&gamepadcontroller);
+        /// Handles the next scripted event then runs one pass of the mappings, returns false once the script is over.
+        fn step(&mut self) -> bool {
+            let running = self.monitor.poll().unwrap();
+
+            let mut events = Vec::new();
+            while let Ok(event) = self.input_queue.try_recv() {
+                events.push(event);
+            }
 
-            self.gamepads
-                .insert(gamepadcontroller.id().unwrap(), gamepadcontroller);
+            let gamepads = self.gamepads.borrow_and_update().clone();
+            self.executor
+                .process_gamepads(events.into_iter(), &gamepads, &None, Instant::now());
 
-            return Ok(gamepad);
+            running
         }
+    }
 
-        Err("No gamepad found".to_string().into())
+    /// Script playing the events back to back.
+    fn script(events: Vec<ScriptedEvent>) -> ScriptedBackend {
+        ScriptedBackend::new(
+            events
+                .into_iter()
+                .map(|event| ScriptStep { delay_ms: 0, event })
+                .collect(),
+        )
     }
-}
 
-pub fn sdl_button_to_gamepad_button(button: &Button) -> GamepadButton {
-    match button {
-        Button::South => GamepadButton::A,
-        Button::East => GamepadButton::B,
-        Button::West => GamepadButton::X,
-        Button::North => GamepadButton::Y,
-        Button::Back => GamepadButton::Back,
-        Button::Guide => GamepadButton::Guide,
-        Button::Start => GamepadButton::Start,
-        Button::LeftStick => GamepadButton::LeftStick,
-        Button::RightStick => GamepadButton::RightStick,
-        Button::LeftShoulder => GamepadButton::LeftShoulder,
-        Button::RightShoulder => GamepadButton::RightShoulder,
-        Button::DPadUp => GamepadButton::DPadUp,
-        Button::DPadDown => GamepadButton::DPadDown,
-        Button::DPadLeft => GamepadButton::DPadLeft,
-        Button::DPadRight => GamepadButton::DPadRight,
-        Button::Misc1 => GamepadButton::Misc1,
-        Button::LeftPaddle1 => GamepadButton::Paddle1,
-        Button::RightPaddle1 => GamepadButton::Paddle2,
-        Button::LeftPaddle2 => GamepadButton::Paddle3,
-        Button::RightPaddle2 => GamepadButton::Paddle4,
-        Button::Touchpad => GamepadButton::Touchpad,
-        _ => GamepadButton::A,
+    fn connect(id: u32) -> ScriptedEvent {
+        ScriptedEvent::Connect {
+            id,
+            name: format!("Pad {}", id),
+            has_gyro: false,
+            has_accel: false,
+            identity: None,
+        }
+    }
+
+    fn button(id: u32, pressed: bool) -> ScriptedEvent {
+        ScriptedEvent::Button {
+            id,
+            button: GamepadButton::A,
+            pressed,
+        }
+    }
+
+    fn press_key(key: &str) -> Action {
+        Action::PressKeys {
+            keys: vec![key.to_string()],
+        }
+    }
+
+    fn key_mapping(key: &str) -> Mapping {
+        Mapping::ButtonPressed(button_mapping("a", GamepadButton::A, press_key(key)))
+    }
+
+    fn player_slot(id: u32) -> Option<u8> {
+        GAMEPAD_STATE
+            .read()
+            .unwrap()
+            .gamepads
+            .get(&id)
+            .map(GamepadState::player_slot)
+    }
+
+    #[test]
+    fn replugging_cleans_up_after_each_removal() {
+        let _globals = GLOBALS.lock().unwrap();
+
+        let mut events = vec![connect(1)];
+        for _ in 0..3 {
+            events.extend([
+                connect(2),
+                button(2, true),
+                ScriptedEvent::Disconnect { id: 2 },
+            ]);
+        }
+        events.push(connect(3));
+
+        let mut pipeline = Pipeline::new(script(events), vec![key_mapping("a")]);
+        pipeline.step();
+
+        for round in 1..=3 {
+            pipeline.step();
+            assert_eq!(player_slot(2), Some(2));
+
+            pipeline.step();
+            pipeline.step();
+            assert_eq!(player_slot(2), None);
+            assert!(!pipeline.gamepads.borrow().contains_key(&2));
+
+            // Unplugged while held: released, and pressed again from scratch once replugged
+            assert_eq!(pipeline.sink.events().len(), round * 2);
+            assert_eq!(
+                pipeline.sink.events()[round * 2 - 2..],
+                [
+                    SinkEvent::KeyDown("a".to_string()),
+                    SinkEvent::KeyUp("a".to_string())
+                ]
+            );
+        }
+
+        pipeline.step();
+        assert_eq!(player_slot(1), Some(1));
+        assert_eq!(player_slot(3), Some(2));
+        assert_eq!(GAMEPAD_STATE.read().unwrap().gamepads.len(), 2);
+    }
+
+    #[test]
+    fn scripted_input_reaches_the_mappings() {
+        let _globals = GLOBALS.lock().unwrap();
+
+        let backend = ScriptedBackend::from_json(
+            r#"[
+                { "event": { "type": "connect", "id": 1, "name": "Scripted pad" } },
+                { "delayMs": 20, "event": { "type": "button", "id": 1, "button": "a", "pressed": true } },
+                { "delayMs": 30, "event": { "type": "button", "id": 1, "button": "a", "pressed": false } },
+                { "delayMs": 10, "event": { "type": "button", "id": 1, "button": "a", "pressed": true } }
+            ]"#,
+        )
+        .unwrap();
+
+        let mut pipeline = Pipeline::new(backend, vec![key_mapping("a")]);
+        while pipeline.step() {}
+
+        assert_eq!(
+            pipeline.sink.events(),
+            vec![
+                SinkEvent::KeyDown("a".to_string()),
+                SinkEvent::KeyUp("a".to_string()),
+                SinkEvent::KeyDown("a".to_string())
+            ]
+        );
+        assert!(GAMEPAD_STATE.read().unwrap().gamepads[&1].is_button_pressed(&GamepadButton::A));
+    }
+
+    #[test]
+    fn feedback_rumble_is_sent_to_the_pressing_pad() {
+        let _globals = GLOBALS.lock().unwrap();
+
+        let rumble = Rumble {
+            low_frequency: 200,
+            high_frequency: 100,
+            left_trigger: 0,
+            right_trigger: 0,
+            duration_ms: 150,
+        };
+        let mapping = Mapping::ButtonPressed(ButtonMapping {
+            feedback: Some(rumble.clone()),
+            ..button_mapping("a", GamepadButton::A, press_key("a"))
+        });
+
+        let events = vec![connect(1), connect(2), button(2, true), button(2, false)];
+        let mut pipeline = Pipeline::new(script(events), vec![mapping]);
+        while pipeline.step() {}
+        pipeline.monitor.send_pending_commands();
+
+        assert_eq!(
+            *pipeline.sent_commands.lock().unwrap(),
+            vec![BackendCommand::Rumble { id: 2, rumble }]
+        );
+    }
+
+    #[test]
+    fn low_battery_is_warned_when_plugged_in() {
+        let _globals = GLOBALS.lock().unwrap();
+
+        let mut pipeline = Pipeline::new(script(vec![]), vec![]);
+        let mut warnings = GAMEPAD_STATE.read().unwrap().subscribe_low_battery();
+
+        let mut gamepad = GamepadState::new(1, "Pad 1".to_string());
+        gamepad.set_power(PowerState {
+            battery_status: BatteryStatus::OnBattery,
+            battery_percent: Some(5),
+            ..PowerState::default()
+        });
+        pipeline
+            .monitor
+            .handle_event(BackendEvent::DeviceAdded(Box::new(gamepad)));
+
+        let warning = warnings.try_recv().unwrap();
+        assert_eq!(warning.gamepad_id, 1);
+        assert_eq!(warning.battery_percent, 5);
     }
-}
 
-pub fn sdl_axis_to_gamepad_axis(axis: &Axis) -> GamepadAxis {
-    match axis {
-        Axis::LeftX => GamepadAxis::LeftX,
-        Axis::LeftY => GamepadAxis::LeftY,
-        Axis::RightX => GamepadAxis::RightX,
-        Axis::RightY => GamepadAxis::RightY,
-        Axis::TriggerLeft => GamepadAxis::TriggerLeft,
-        Axis::TriggerRight => GamepadAxis::TriggerRight,
+    #[test]
+    fn sensor_samples_wake_the_watchers_at_most_once_per_interval() {
+        let _globals = GLOBALS.lock().unwrap();
+
+        let sample = |x: f32| ScriptedEvent::Sensor {
+            id: 1,
+            sensor: MotionSensor::Gyroscope,
+            reading: SensorReading {
+                x,
+                ..SensorReading::default()
+            },
+        };
+        let mut pipeline =
+            Pipeline::new(script(vec![connect(1), sample(1.0), sample(2.0)]), vec![]);
+
+        pipeline.step();
+        pipeline.monitor.poll().unwrap();
+        assert!(pipeline.gamepads.has_changed().unwrap());
+        pipeline.gamepads.mark_unchanged();
+
+        // Right after the first sample, stored without waking anyone
+        pipeline.monitor.poll().unwrap();
+        assert!(!pipeline.gamepads.has_changed().unwrap());
+
+        let gyro = pipeline.gamepads.borrow()[&1].motion().gyro;
+        assert_eq!(gyro.map(|reading| reading.x), Some(2.0));
     }
 }
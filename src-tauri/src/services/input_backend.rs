@@ -0,0 +1,98 @@
+use std::{env, error::Error, time::Duration};
+
+use crate::{
+    model::gamepad::{
+        GamepadAxis, GamepadButton, GamepadState, HatPosition, LedColor, MotionSensor, PowerState,
+        SensorReading, TouchpadFinger,
+    },
+    model::mapping::Rumble,
+    model::sdl_mapping::RawInput,
+    services::{scripted_backend::ScriptedBackend, sdl_backend::SdlBackend},
+};
+
+const BACKEND_ENV: &str = "JOYCTRL_INPUT_BACKEND";
+const SCRIPT_ENV: &str = "JOYCTRL_INPUT_SCRIPT";
+
+/// Event produced by an input backend, applied to `GAMEPAD_STATE` by the gamepad monitor.
+#[derive(Debug, Clone)]
+pub enum BackendEvent {
+    /// Boxed, the state is far larger than the other events
+    DeviceAdded(Box<GamepadState>),
+    DeviceRemoved {
+        id: u32,
+    },
+    Button {
+        id: u32,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    Axis {
+        id: u32,
+        axis: GamepadAxis,
+        value: i16,
+    },
+    Hat {
+        id: u32,
+        hat: u8,
+        position: HatPosition,
+    },
+    Sensor {
+        id: u32,
+        sensor: MotionSensor,
+        reading: SensorReading,
+    },
+    Touchpad {
+        id: u32,
+        finger: TouchpadFinger,
+        pressed: bool,
+    },
+    Power {
+        id: u32,
+        power: PowerState,
+    },
+    /// Only emitted for devices with raw capture enabled, in place of their regular input events
+    RawInput {
+        id: u32,
+        input: RawInput,
+    },
+    Quit,
+}
+
+/// Output sent back to a controller through its backend.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendCommand {
+    Rumble { id: u32, rumble: Rumble },
+    SetLed { id: u32, color: LedColor },
+    AddSdlMappings { mappings: Vec<String> },
+    SetRawCapture { id: u32, enabled: bool },
+}
+
+/// Source of gamepad events (real hardware through SDL, or a scripted replay).
+pub trait InputBackend {
+    fn name(&self) -> &'static str;
+
+    /// Waits up to `timeout` for the next event, returns `None` if nothing happened in time.
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<BackendEvent>, Box<dyn Error>>;
+
+    /// Whether the backend will never produce another event (end of a script).
+    fn is_exhausted(&self) -> bool {
+        false
+    }
+
+    fn send_command(&mut self, command: &BackendCommand) -> Result<(), Box<dyn Error>>;
+}
+
+/// Picks the backend from `JOYCTRL_INPUT_BACKEND` ("sdl" by default, or "scripted" with `JOYCTRL_INPUT_SCRIPT` pointing to a json script).
+pub fn backend_from_env() -> Result<Box<dyn InputBackend>, Box<dyn Error>> {
+    let backend = env::var(BACKEND_ENV).unwrap_or_else(|_| "sdl".to_string());
+
+    match backend.to_lowercase().as_str() {
+        "sdl" => Ok(Box::new(SdlBackend::new()?)),
+        "scripted" => {
+            let path = env::var(SCRIPT_ENV)
+                .map_err(|_| format!("{} must be set to use the scripted backend", SCRIPT_ENV))?;
+            Ok(Box::new(ScriptedBackend::from_file(&path)?))
+        }
+        other => Err(format!("Unknown input backend: {}", other).into()),
+    }
+}
@@ -0,0 +1,116 @@
+use std::error::Error;
+
+use enigo::{Axis, Button as EnigoButton, Coordinate, Direction, Enigo, Keyboard, Mouse};
+
+use crate::{model::mapping::MouseButton, services::virtual_keyboard};
+
+/// Destination of the keyboard and mouse input produced by the mappings, the system through Enigo outside of tests.
+pub trait InputSink: Send {
+    fn press_key(&mut self, key: &str) -> Result<(), Box<dyn Error>>;
+    fn release_key(&mut self, key: &str) -> Result<(), Box<dyn Error>>;
+    fn text(&mut self, text: &str) -> Result<(), Box<dyn Error>>;
+    fn mouse_button(
+        &mut self,
+        button: &MouseButton,
+        direction: Direction,
+    ) -> Result<(), Box<dyn Error>>;
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), Box<dyn Error>>;
+    fn scroll(&mut self, length: i32, axis: Axis) -> Result<(), Box<dyn Error>>;
+}
+
+impl InputSink for Enigo {
+    fn press_key(&mut self, key: &str) -> Result<(), Box<dyn Error>> {
+        virtual_keyboard::press_key(key, self, false)
+    }
+
+    fn release_key(&mut self, key: &str) -> Result<(), Box<dyn Error>> {
+        virtual_keyboard::release_key(key, self)
+    }
+
+    fn text(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        Keyboard::text(self, text).map_err(Into::into)
+    }
+
+    fn mouse_button(
+        &mut self,
+        button: &MouseButton,
+        direction: Direction,
+    ) -> Result<(), Box<dyn Error>> {
+        let button = match button {
+            MouseButton::Left => EnigoButton::Left,
+            MouseButton::Right => EnigoButton::Right,
+            MouseButton::Middle => EnigoButton::Middle,
+        };
+
+        self.button(button, direction).map_err(Into::into)
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), Box<dyn Error>> {
+        Mouse::move_mouse(self, x, y, coordinate).map_err(Into::into)
+    }
+
+    fn scroll(&mut self, length: i32, axis: Axis) -> Result<(), Box<dyn Error>> {
+        Mouse::scroll(self, length, axis).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SinkEvent {
+    KeyDown(String),
+    KeyUp(String),
+    Text(String),
+    MouseButton(MouseButton, Direction),
+    MoveMouse(i32, i32, Coordinate),
+    Scroll(i32, Axis),
+}
+
+/// Records the input instead of sending it, clones share the same record.
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub struct RecordingSink {
+    events: std::sync::Arc<std::sync::Mutex<Vec<SinkEvent>>>,
+}
+
+#[cfg(test)]
+impl RecordingSink {
+    pub fn events(&self) -> Vec<SinkEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    fn record(&self, event: SinkEvent) -> Result<(), Box<dyn Error>> {
+        self.events.lock().unwrap().push(event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl InputSink for RecordingSink {
+    fn press_key(&mut self, key: &str) -> Result<(), Box<dyn Error>> {
+        self.record(SinkEvent::KeyDown(key.to_string()))
+    }
+
+    fn release_key(&mut self, key: &str) -> Result<(), Box<dyn Error>> {
+        self.record(SinkEvent::KeyUp(key.to_string()))
+    }
+
+    fn text(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        self.record(SinkEvent::Text(text.to_string()))
+    }
+
+    fn mouse_button(
+        &mut self,
+        button: &MouseButton,
+        direction: Direction,
+    ) -> Result<(), Box<dyn Error>> {
+        self.record(SinkEvent::MouseButton(button.clone(), direction))
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), Box<dyn Error>> {
+        self.record(SinkEvent::MoveMouse(x, y, coordinate))
+    }
+
+    fn scroll(&mut self, length: i32, axis: Axis) -> Result<(), Box<dyn Error>> {
+        self.record(SinkEvent::Scroll(length, axis))
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use crate::{
+    model::{
+        config::Config,
+        gamepad::{GamepadState, LedColor},
+    },
+    services::{
+        config_service::CONFIG_SERVICE,
+        gamepad_service::{GAMEPAD_STATE, send_backend_command},
+        input_backend::BackendCommand,
+        mapping_service::{get_mapping_active, watch_mapping_active},
+    },
+};
+
+/// Keeps every controller lightbar in sync with its configured colour and the mapping state.
+pub async fn start_led_sync() -> Result<(), Box<dyn std::error::Error>> {
+    let config_service = CONFIG_SERVICE
+        .get()
+        .ok_or("Config service not initialized")?;
+
+    let mut config_rx = config_service.watch();
+    let mut gamepads_rx = { GAMEPAD_STATE.read().unwrap().watch_gamepads() };
+    let mut active_rx = watch_mapping_active().ok_or("Mapping active watcher not available")?;
+
+    let mut applied: HashMap<u32, LedColor> = HashMap::new();
+    let mut devices = connected_devices(&gamepads_rx.borrow_and_update());
+
+    loop {
+        {
+            let config = config_rx.borrow_and_update();
+            let gamepads = gamepads_rx.borrow();
+            let active = get_mapping_active();
+            active_rx.borrow_and_update();
+
+            applied.retain(|id, _| gamepads.contains_key(id));
+
+            for gamepad in gamepads.values() {
+                let Some(color) = resolve_led_color(&config, gamepad, active) else {
+                    continue;
+                };
+
+                if applied.get(&gamepad.id()) == Some(&color) {
+                    continue;
+                }
+
+                applied.insert(gamepad.id(), color);
+                send_backend_command(BackendCommand::SetLed {
+                    id: gamepad.id(),
+                    color,
+                });
+            }
+        }
+
+        // Button, axis and sensor changes don't affect the colours, only wake up for the devices coming and going
+        loop {
+            tokio::select! {
+                changed = config_rx.changed() => match changed {
+                    Ok(()) => break,
+                    Err(_) => return Ok(()),
+                },
+                changed = active_rx.changed() => match changed {
+                    Ok(()) => break,
+                    Err(_) => return Ok(()),
+                },
+                changed = gamepads_rx.changed() => {
+                    if changed.is_err() {
+                        return Ok(());
+                    }
+
+                    let current = connected_devices(&gamepads_rx.borrow_and_update());
+                    if current != devices {
+                        devices = current;
+                        break;
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Identity key of every connected gamepad, what the colours depend on.
+fn connected_devices(gamepads: &HashMap<u32, GamepadState>) -> HashMap<u32, String> {
+    gamepads
+        .iter()
+        .map(|(id, gamepad)| (*id, gamepad.identity().key()))
+        .collect()
+}
+
+/// Paused colour first when mappings are off, then the active profile's colour, the controller's own colour,
+/// and the global active colour.
+pub fn resolve_led_color(
+    config: &Config,
+    gamepad: &GamepadState,
+    active: bool,
+) -> Option<LedColor> {
+    let profile_color = config
+        .active_profile()
+        .and_then(|profile| profile.led_color);
+    let device_color = config
+        .known_device(&gamepad.identity().key())
+        .and_then(|device| device.led_color);
+
+    if !active && config.led_colors.paused.is_some() {
+        return config.led_colors.paused;
+    }
+
+    profile_color.or(device_color).or(config.led_colors.active)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::config::KnownDevice;
+
+    const PAUSED: LedColor = LedColor { r: 255, g: 0, b: 0 };
+    const PROFILE: LedColor = LedColor { r: 0, g: 255, b: 0 };
+    const DEVICE: LedColor = LedColor { r: 0, g: 0, b: 255 };
+    const ACTIVE: LedColor = LedColor {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+
+    #[test]
+    fn led_color_priority() {
+        let gamepad = GamepadState::new(1, "Pad 1".to_string());
+        let mut config = Config::default();
+        config.led_colors.active = Some(ACTIVE);
+        config.led_colors.paused = Some(PAUSED);
+
+        assert_eq!(resolve_led_color(&config, &gamepad, true), Some(ACTIVE));
+
+        let mut device = KnownDevice::new(gamepad.identity().clone());
+        device.led_color = Some(DEVICE);
+        config.known_devices.push(device);
+        assert_eq!(resolve_led_color(&config, &gamepad, true), Some(DEVICE));
+
+        config.ensure_profiles();
+        config.profiles[0].led_color = Some(PROFILE);
+        assert_eq!(resolve_led_color(&config, &gamepad, true), Some(PROFILE));
+        assert_eq!(resolve_led_color(&config, &gamepad, false), Some(PAUSED));
+    }
+}
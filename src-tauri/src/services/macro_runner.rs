@@ -0,0 +1,308 @@
+use std::{sync::Arc, time::Duration};
+
+use enigo::{Axis, Coordinate, Direction, Enigo, Settings};
+use tokio::{sync::watch, time::sleep};
+
+use crate::{
+    model::mapping::{Action, MacroSequence, MacroStep},
+    services::{input_sink::InputSink, mapping_service::perform_system_action},
+};
+
+/// Handle on a macro running in the background.
+#[derive(Debug, Clone)]
+pub struct RunningMacro {
+    cancel: Arc<watch::Sender<bool>>,
+    cancel_on_release: bool,
+}
+
+impl RunningMacro {
+    /// The task drops its receiver when the macro ends.
+    pub fn is_running(&self) -> bool {
+        !self.cancel.is_closed()
+    }
+
+    pub fn cancel_on_release(&self) -> bool {
+        self.cancel_on_release
+    }
+
+    pub fn cancel(&self) {
+        let _ = self.cancel.send(true);
+    }
+
+    /// Handle without a task behind it, the receiver tells whether it got cancelled.
+    #[cfg(test)]
+    pub fn detached(cancel_on_release: bool) -> (Self, watch::Receiver<bool>) {
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+
+        let running = Self {
+            cancel: Arc::new(cancel_tx),
+            cancel_on_release,
+        };
+
+        (running, cancel_rx)
+    }
+}
+
+/// Starts the macro on its own task with its own Enigo, `gamepad_id` is the controller rumble and LED steps apply to.
+pub fn spawn_macro(sequence: &MacroSequence, gamepad_id: u32) -> RunningMacro {
+    let (cancel_tx, cancel_rx) = watch::channel(false);
+    let steps = sequence.steps.clone();
+
+    tokio::spawn(async move {
+        let mut enigo = match Enigo::new(&Settings::default()) {
+            Ok(enigo) => enigo,
+            Err(e) => {
+                eprintln!("Unable to start macro: {}", e);
+                return;
+            }
+        };
+
+        run_macro(&steps, gamepad_id, &mut enigo, cancel_rx).await;
+    });
+
+    RunningMacro {
+        cancel: Arc::new(cancel_tx),
+        cancel_on_release: sequence.cancel_on_release,
+    }
+}
+
+/// Runs the steps until they end or the macro is cancelled, then releases the keys still held.
+async fn run_macro(
+    steps: &[MacroStep],
+    gamepad_id: u32,
+    sink: &mut dyn InputSink,
+    mut cancel: watch::Receiver<bool>,
+) {
+    let mut held_keys: Vec<String> = Vec::new();
+
+    run_steps(steps, gamepad_id, sink, &mut held_keys, &mut cancel).await;
+
+    // Cancelled halfway or missing its key up, the keys would stay stuck
+    for key in held_keys.iter().rev() {
+        let _ = sink.release_key(key);
+    }
+}
+
+/// Returns `false` once the macro is cancelled.
+async fn run_steps(
+    steps: &[MacroStep],
+    gamepad_id: u32,
+    sink: &mut dyn InputSink,
+    held_keys: &mut Vec<String>,
+    cancel: &mut watch::Receiver<bool>,
+) -> bool {
+    for step in steps {
+        if *cancel.borrow() {
+            return false;
+        }
+
+        match step {
+            MacroStep::KeyDown { key } => {
+                if sink.press_key(key).is_ok() {
+                    held_keys.push(key.clone());
+                }
+            }
+            MacroStep::KeyUp { key } => {
+                let _ = sink.release_key(key);
+                held_keys.retain(|held| held != key);
+            }
+            MacroStep::Text { text } => {
+                let _ = sink.text(text);
+            }
+            MacroStep::MouseClick { button } => {
+                let _ = sink.mouse_button(button, Direction::Click);
+            }
+            MacroStep::MouseMove { x, y } => {
+                let _ = sink.move_mouse(*x, *y, Coordinate::Rel);
+            }
+            MacroStep::Scroll { x, y } => {
+                if *x != 0 {
+                    let _ = sink.scroll(*x, Axis::Horizontal);
+                }
+                if *y != 0 {
+                    let _ = sink.scroll(*y, Axis::Vertical);
+                }
+            }
+            MacroStep::Wait { ms } => {
+                tokio::select! {
+                    _ = sleep(Duration::from_millis(*ms as u64)) => {}
+                    _ = cancelled(cancel) => return false,
+                }
+            }
+            MacroStep::Action { action } => match action.as_ref() {
+                Action::Macro { sequence } => {
+                    let completed = Box::pin(run_steps(
+                        &sequence.steps,
+                        gamepad_id,
+                        sink,
+                        held_keys,
+                        cancel,
+                    ))
+                    .await;
+
+                    if !completed {
+                        return false;
+                    }
+                }
+                action => perform_step_action(action, gamepad_id, sink),
+            },
+        }
+    }
+
+    true
+}
+
+/// Resolves once the macro is cancelled, never if its handle was dropped so it still runs to completion.
+async fn cancelled(cancel: &mut watch::Receiver<bool>) {
+    if cancel.wait_for(|cancelled| *cancelled).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Runs an action once, keys and buttons are pressed then released right away.
+fn perform_step_action(action: &Action, gamepad_id: u32, sink: &mut dyn InputSink) {
+    match action {
+        Action::PressKeys { keys } => {
+            for key in keys.iter() {
+                let _ = sink.press_key(key);
+            }
+            for key in keys.iter().rev() {
+                let _ = sink.release_key(key);
+            }
+        }
+        Action::WriteText { text } => {
+            let _ = sink.text(text);
+        }
+        Action::MouseClick { button } => {
+            let _ = sink.mouse_button(button, Direction::Click);
+        }
+        action => perform_system_action(action, gamepad_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::input_sink::{RecordingSink, SinkEvent};
+
+    fn key_down(key: &str) -> MacroStep {
+        MacroStep::KeyDown {
+            key: key.to_string(),
+        }
+    }
+
+    fn key_up(key: &str) -> MacroStep {
+        MacroStep::KeyUp {
+            key: key.to_string(),
+        }
+    }
+
+    async fn run(steps: Vec<MacroStep>) -> Vec<SinkEvent> {
+        let mut sink = RecordingSink::default();
+        let (_cancel_tx, cancel_rx) = watch::channel(false);
+
+        run_macro(&steps, 1, &mut sink, cancel_rx).await;
+        sink.events()
+    }
+
+    #[tokio::test]
+    async fn steps_run_in_order() {
+        let events = run(vec![
+            key_down("a"),
+            MacroStep::Wait { ms: 1 },
+            MacroStep::Text {
+                text: "hi".to_string(),
+            },
+            MacroStep::MouseMove { x: 3, y: -2 },
+            MacroStep::Scroll { x: 1, y: 2 },
+            key_up("a"),
+        ])
+        .await;
+
+        assert_eq!(
+            events,
+            vec![
+                SinkEvent::KeyDown("a".to_string()),
+                SinkEvent::Text("hi".to_string()),
+                SinkEvent::MoveMouse(3, -2, Coordinate::Rel),
+                SinkEvent::Scroll(1, Axis::Horizontal),
+                SinkEvent::Scroll(2, Axis::Vertical),
+                SinkEvent::KeyUp("a".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn nested_macros_run_inline() {
+        let nested = Action::Macro {
+            sequence: MacroSequence {
+                steps: vec![key_down("b"), key_up("b")],
+                cancel_on_release: false,
+            },
+        };
+
+        let events = run(vec![
+            key_down("a"),
+            MacroStep::Action {
+                action: Box::new(nested),
+            },
+            key_up("a"),
+        ])
+        .await;
+
+        assert_eq!(
+            events,
+            vec![
+                SinkEvent::KeyDown("a".to_string()),
+                SinkEvent::KeyDown("b".to_string()),
+                SinkEvent::KeyUp("b".to_string()),
+                SinkEvent::KeyUp("a".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelling_during_a_wait_releases_the_held_keys() {
+        let steps = vec![
+            key_down("a"),
+            key_down("b"),
+            MacroStep::Wait { ms: 10_000 },
+            MacroStep::Text {
+                text: "never".to_string(),
+            },
+        ];
+        let mut sink = RecordingSink::default();
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+
+        let cancel = async {
+            sleep(Duration::from_millis(20)).await;
+            let _ = cancel_tx.send(true);
+        };
+        tokio::join!(run_macro(&steps, 1, &mut sink, cancel_rx), cancel);
+
+        assert_eq!(
+            sink.events(),
+            vec![
+                SinkEvent::KeyDown("a".to_string()),
+                SinkEvent::KeyDown("b".to_string()),
+                SinkEvent::KeyUp("b".to_string()),
+                SinkEvent::KeyUp("a".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keys_left_down_are_released_at_the_end() {
+        let events = run(vec![key_down("a"), key_down("b"), key_up("a")]).await;
+
+        assert_eq!(
+            events,
+            vec![
+                SinkEvent::KeyDown("a".to_string()),
+                SinkEvent::KeyDown("b".to_string()),
+                SinkEvent::KeyUp("a".to_string()),
+                SinkEvent::KeyUp("b".to_string()),
+            ]
+        );
+    }
+}
@@ -8,19 +8,36 @@ use std::{
 };
 
 use crate::{
-    APP, get_app_handle,
+    get_app_handle,
     model::{
-        config::Config,
-        gamepad::{GamepadAxis, GamepadState},
+        config::{Config, LayerMode},
+        gamepad::{
+            GamepadAxis, GamepadButton, GamepadState, InputEvent, InputEventKind, SensorReading,
+            TouchpadFinger,
+        },
         mapping::*,
     },
-    services::{config_service::CONFIG_SERVICE, gamepad_service::GAMEPAD_STATE, virtual_keyboard},
+    services::{
+        config_service::{CONFIG_SERVICE, ConfigService},
+        gamepad_service::{GAMEPAD_STATE, send_backend_command},
+        input_backend::BackendCommand,
+        input_sink::InputSink,
+        macro_runner::{RunningMacro, spawn_macro},
+        virtual_keyboard,
+    },
 };
 
-use enigo::{Axis, Button as EnigoButton, Coordinate, Enigo, Keyboard, Mouse, Settings};
-use tauri::AppHandle;
+use enigo::{Axis, Coordinate, Enigo, Settings};
 use tauri_plugin_opener::OpenerExt;
-use tokio::{sync::watch, time::sleep};
+use tokio::{
+    sync::watch,
+    time::{MissedTickBehavior, interval, sleep, sleep_until},
+};
+
+/// Rate at which continuous actions (cursor, scroll) are updated.
+const TICK_INTERVAL: Duration = Duration::from_millis(8);
+/// Longest time step a single move accounts for, avoids a jump after a stall.
+const MAX_MOVE_STEP: Duration = Duration::from_millis(50);
 
 static MAPPING_ACTIVE: AtomicBool = AtomicBool::new(true);
 static MAPPING_ACTIVE_WATCHER: OnceLock<watch::Sender<bool>> = OnceLock::new();
@@ -29,50 +46,137 @@ pub fn get_mapping_active() -> bool {
     MAPPING_ACTIVE.load(Ordering::Relaxed)
 }
 
+fn mapping_active_sender() -> &'static watch::Sender<bool> {
+    MAPPING_ACTIVE_WATCHER.get_or_init(|| {
+        let (tx, _) = watch::channel(get_mapping_active());
+        tx
+    })
+}
+
 pub fn set_mapping_active(set: bool) {
     MAPPING_ACTIVE.store(set, Ordering::Relaxed);
-    let sender = MAPPING_ACTIVE_WATCHER.get_or_init(|| {
-        let (tx, _) = watch::channel(set);
-        tx
-    });
-    let _ = sender.send(set);
+    let _ = mapping_active_sender().send(set);
 }
 
 pub fn watch_mapping_active() -> Option<watch::Receiver<bool>> {
-    MAPPING_ACTIVE_WATCHER
-        .get()
-        .map(|sender| sender.subscribe())
+    Some(mapping_active_sender().subscribe())
+}
+
+/// Executor state is tracked per gamepad and per mapping, so two controllers using the same mapping don't overwrite each other.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionKey {
+    gamepad_id: u32,
+    mapping_id: String,
+}
+
+impl ActionKey {
+    pub fn new(gamepad: &GamepadState, mapping_id: &str) -> Self {
+        Self {
+            gamepad_id: gamepad.id(),
+            mapping_id: mapping_id.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RatchetState {
+    latched: bool,
+    was_pressed: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct TurboState {
+    was_pressed: bool,
+    latched: bool,
+    down: bool,
+    /// End of the current phase, `None` while the turbo isn't running
+    phase_end: Option<Instant>,
+}
+
+/// Layers switched on from a gamepad, the last one is on top.
+#[derive(Debug, Clone, Default)]
+struct LayerState {
+    active: Vec<String>,
+    /// Armed one-shot layers, with the button that uses them once it is pressed
+    one_shots: HashMap<String, Option<GamepadButton>>,
+}
+
+impl LayerState {
+    fn is_active(&self, id: &str) -> bool {
+        self.active.iter().any(|layer| layer == id)
+    }
+
+    fn set_active(&mut self, id: &str, active: bool) {
+        match (active, self.is_active(id)) {
+            (true, false) => self.active.push(id.to_string()),
+            (false, true) => {
+                self.active.retain(|layer| layer != id);
+                self.one_shots.remove(id);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct TouchpadTracking {
+    previous_fingers: Vec<TouchpadFinger>,
+    gesture_start: Option<Instant>,
+    gesture_travel: f32,
+    gesture_max_fingers: usize,
+    last_x: f32,
+    pressed_button: Option<MouseButton>,
 }
 
 #[derive(Debug, Clone)]
 pub struct MappingState {
-    scroll_speed: i32,
-    active_actions: HashMap<String, Instant>,
-    continuous_actions: HashSet<String>,
-    pressed_buttons: HashMap<String, bool>,
-    previous_gamepad_state: Option<GamepadState>,
+    active_actions: HashMap<ActionKey, Instant>,
+    continuous_actions: HashSet<ActionKey>,
+    pressed_buttons: HashMap<ActionKey, bool>,
+    previous_gamepad_states: HashMap<u32, GamepadState>,
+    gyro_timestamps: HashMap<ActionKey, u64>,
+    gyro_ratchets: HashMap<ActionKey, RatchetState>,
+    mouse_remainders: HashMap<ActionKey, (f32, f32)>,
+    scroll_accumulators: HashMap<ActionKey, (f32, f32)>,
+    last_moves: HashMap<ActionKey, Instant>,
+    touchpads: HashMap<ActionKey, TouchpadTracking>,
+    button_triggers: HashMap<ActionKey, ButtonTriggerTracker>,
+    /// Mappings processed during the current pass, the trackers of the others are dropped at its end
+    evaluated: HashSet<ActionKey>,
+    macros: HashMap<ActionKey, RunningMacro>,
+    turbos: HashMap<ActionKey, TurboState>,
+    layers: HashMap<u32, LayerState>,
+    /// Mappings resolved on the last pass, by gamepad
+    resolved: HashMap<u32, Vec<Mapping>>,
 }
 
 impl MappingState {
     pub fn new() -> Self {
         Self {
-            scroll_speed: 20, // Between 0 and 100
             continuous_actions: HashSet::new(),
             active_actions: HashMap::new(),
             pressed_buttons: HashMap::new(),
-            previous_gamepad_state: None,
+            previous_gamepad_states: HashMap::new(),
+            gyro_timestamps: HashMap::new(),
+            gyro_ratchets: HashMap::new(),
+            mouse_remainders: HashMap::new(),
+            scroll_accumulators: HashMap::new(),
+            last_moves: HashMap::new(),
+            touchpads: HashMap::new(),
+            button_triggers: HashMap::new(),
+            evaluated: HashSet::new(),
+            macros: HashMap::new(),
+            turbos: HashMap::new(),
+            layers: HashMap::new(),
+            resolved: HashMap::new(),
         }
     }
 }
 
 pub struct MappingExecutor {
-    app: AppHandle,
-    enigo: Enigo,
+    sink: Box<dyn InputSink>,
     config: Config,
     mapping_state: MappingState,
-    scroll_accumulator_x: f32,
-    scroll_accumulator_y: f32,
-    last_scroll_time: Instant,
 }
 
 impl MappingExecutor {
@@ -83,20 +187,39 @@ impl MappingExecutor {
             set_mapping_active(config_service.get_config().mapping_active_on_boot);
         }
 
-        Ok(Self {
-            app: get_app_handle(),
-            enigo,
+        Ok(Self::with_sink(Box::new(enigo)))
+    }
+
+    pub fn with_sink(sink: Box<dyn InputSink>) -> Self {
+        Self {
+            sink,
             config: Config::default(),
             mapping_state: MappingState::new(),
-            scroll_accumulator_x: 0.0,
-            scroll_accumulator_y: 0.0,
-            last_scroll_time: Instant::now(),
-        })
+        }
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        if config.active_profile != self.config.active_profile {
+            self.cancel_all_macros();
+        }
+
+        self.config = config;
     }
 
     pub async fn start_mapping_loop() -> Result<(), Box<dyn std::error::Error>> {
         let mut executor = MappingExecutor::new()?;
-        let mut watcher = { GAMEPAD_STATE.read().unwrap().watch_gamepads() };
+        let (mut watcher, mut input_queue) = {
+            let mut state = GAMEPAD_STATE.write().unwrap();
+            (state.watch_gamepads(), state.subscribe_input_queue())
+        };
+        let mut config_rx = CONFIG_SERVICE.get().unwrap().watch();
+        let mut input_events: Vec<InputEvent> = Vec::new();
+
+        executor.set_config(config_rx.borrow_and_update().clone());
+        let mut gamepads_map = watcher.borrow_and_update().clone();
+
+        let mut ticker = interval(TICK_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
         loop {
             let mapping_active = MAPPING_ACTIVE.load(Ordering::Relaxed);
@@ -112,80 +235,182 @@ impl MappingExecutor {
             }
 
             let has_continuous_actions = !executor.mapping_state.continuous_actions.is_empty();
+            let trigger_deadline = executor.next_trigger_deadline();
 
-            if has_continuous_actions {
-                tokio::select! {
-                    _ = watcher.changed() => {}
-                    _ = sleep(Duration::from_millis(8)) => {}
-                }
-            } else if watcher.changed().await.is_err() {
-                continue;
+            tokio::select! {
+                changed = watcher.changed() => if changed.is_err() { continue; },
+                Some(event) = input_queue.recv() => input_events.push(event),
+                _ = ticker.tick(), if has_continuous_actions => {}
+                _ = sleep_until(trigger_deadline.unwrap_or_else(Instant::now).into()), if trigger_deadline.is_some() => {}
             }
 
-            executor.config = {
-                let config = CONFIG_SERVICE.get().unwrap();
-                config.get_config()
+            while let Ok(event) = input_queue.try_recv() {
+                input_events.push(event);
+            }
+
+            // Ticks and trigger deadlines reuse the last snapshots when nothing changed
+            if config_rx.has_changed().unwrap_or(false) {
+                executor.set_config(config_rx.borrow_and_update().clone());
+            }
+
+            if watcher.has_changed().unwrap_or(false) {
+                gamepads_map = watcher.borrow_and_update().clone();
+            }
+
+            executor.process_gamepads(
+                input_events.drain(..),
+                &gamepads_map,
+                &allowed_actions,
+                Instant::now(),
+            );
+        }
+    }
+
+    /// One pass of the loop: the queued edges first, then the state of every gamepad at `now`.
+    pub fn process_gamepads(
+        &mut self,
+        events: impl Iterator<Item = InputEvent>,
+        gamepads_map: &HashMap<u32, GamepadState>,
+        allowed_actions: &Option<Vec<Action>>,
+        now: Instant,
+    ) {
+        // Paused, nothing should keep typing
+        if allowed_actions.is_some() {
+            self.cancel_all_macros();
+        }
+
+        self.mapping_state.evaluated.clear();
+        self.replay_button_edges(events, gamepads_map, allowed_actions);
+
+        for gamepad in gamepads_map.values() {
+            self.process_gamepad_state(gamepad, allowed_actions, false, now);
+        }
+
+        self.drop_skipped_mappings();
+
+        let connected: HashSet<u32> = gamepads_map.keys().copied().collect();
+        self.release_disconnected_gamepads(&connected);
+    }
+
+    /// Processes the button mappings once per queued edge, in order, so a press and release
+    /// arriving between two iterations still fire the action.
+    fn replay_button_edges(
+        &mut self,
+        events: impl Iterator<Item = InputEvent>,
+        gamepads: &HashMap<u32, GamepadState>,
+        allowed_actions: &Option<Vec<Action>>,
+    ) {
+        for event in events {
+            let base = self
+                .mapping_state
+                .previous_gamepad_states
+                .get(&event.gamepad_id)
+                .or_else(|| gamepads.get(&event.gamepad_id));
+
+            let Some(mut gamepad) = base.cloned() else {
+                continue;
             };
 
-            let gamepads_map = watcher.borrow_and_update();
-            let gamepads: Vec<&GamepadState> = gamepads_map.values().collect();
-            gamepads.into_iter().for_each(|gamepad| {
-                executor.process_gamepad_state(gamepad, &allowed_actions);
-            });
+            match event.kind {
+                InputEventKind::ButtonDown { button } => gamepad.set_button(button, true),
+                InputEventKind::ButtonUp { button } => gamepad.set_button(button, false),
+                InputEventKind::AxisChanged { axis, value } => {
+                    // Axes are continuous, only keep the value for the next edge
+                    gamepad.set_calibrated_axis(axis, value);
+                    self.mapping_state
+                        .previous_gamepad_states
+                        .insert(gamepad.id(), gamepad);
+                    continue;
+                }
+            }
+
+            self.process_gamepad_state(&gamepad, allowed_actions, true, event.instant());
         }
     }
 
+    /// With `buttons_only`, only the button mappings are processed (edge replay).
+    /// `now` is when the state was reached, it times the button triggers.
     fn process_gamepad_state(
         &mut self,
         gamepad: &GamepadState,
         allowed_actions: &Option<Vec<Action>>,
+        buttons_only: bool,
+        now: Instant,
     ) {
-        for mapping in self.config.mappings.clone().iter() {
-            if let Some(allowed_actions) = allowed_actions {
-                let action = match mapping {
-                    Mapping::ButtonPressed(mapping) => &mapping.action,
-                    Mapping::AxisTrigger(mapping) => &mapping.action,
-                    Mapping::AxisStick(mapping) => &mapping.action,
-                };
-
-                if !allowed_actions.contains(&action) {
-                    continue;
-                }
+        self.update_layers(gamepad);
+
+        let mappings = self.resolve_mappings(gamepad.id());
+        self.sync_resolved_mappings(gamepad, &mappings);
+
+        for mapping in mappings.iter() {
+            if !mapping.applies_to(gamepad) {
+                continue;
             }
 
+            if buttons_only && !matches!(mapping, Mapping::ButtonPressed(_)) {
+                continue;
+            }
+
+            if let Some(allowed_actions) = allowed_actions
+                && !mapping
+                    .action()
+                    .is_some_and(|action| allowed_actions.contains(action))
+            {
+                continue;
+            }
+
+            self.mapping_state
+                .evaluated
+                .insert(ActionKey::new(gamepad, mapping.id()));
+
             match mapping {
                 Mapping::ButtonPressed(mapping) => {
                     if self.is_action_continuous(&mapping.action) {
                         self.mapping_state
                             .continuous_actions
-                            .insert(mapping.id.clone());
+                            .insert(ActionKey::new(gamepad, &mapping.id));
                     }
 
-                    let once = self.is_action_once(&mapping.action);
-                    self.process_button_mapping(gamepad, mapping, once)
+                    match (mapping.trigger.unwrap_or_default(), &mapping.turbo) {
+                        (ButtonTrigger::Press, Some(turbo)) => {
+                            self.process_turbo_button_mapping(gamepad, mapping, turbo, now)
+                        }
+                        (ButtonTrigger::Press, None) => {
+                            let once = self.is_action_once(&mapping.action);
+                            self.process_button_mapping(gamepad, mapping, once, now)
+                        }
+                        (trigger, _) => {
+                            self.process_triggered_button_mapping(gamepad, mapping, &trigger, now)
+                        }
+                    }
                 }
                 Mapping::AxisTrigger(mapping) => {
                     if self.is_action_continuous(&mapping.action) {
                         self.mapping_state
                             .continuous_actions
-                            .insert(mapping.id.clone());
+                            .insert(ActionKey::new(gamepad, &mapping.id));
                     }
 
-                    self.process_axis_trigger_mapping(gamepad, mapping)
+                    self.process_axis_trigger_mapping(gamepad, mapping, now)
                 }
                 Mapping::AxisStick(mapping) => {
                     if self.is_action_continuous(&mapping.action) {
                         self.mapping_state
                             .continuous_actions
-                            .insert(mapping.id.clone());
+                            .insert(ActionKey::new(gamepad, &mapping.id));
                     }
 
-                    self.process_axis_stick_mapping(gamepad, mapping)
+                    self.process_axis_stick_mapping(gamepad, mapping, now)
                 }
+                Mapping::Touchpad(mapping) => self.process_touchpad_mapping(gamepad, mapping, now),
             }
         }
 
-        self.mapping_state.previous_gamepad_state = Some(gamepad.clone());
+        self.end_one_shot_layers(gamepad);
+
+        self.mapping_state
+            .previous_gamepad_states
+            .insert(gamepad.id(), gamepad.clone());
     }
 
     fn process_button_mapping(
@@ -193,9 +418,10 @@ impl MappingExecutor {
         gamepad: &GamepadState,
         mapping: &ButtonMapping,
         once: bool,
+        now: Instant,
     ) {
         let is_pressed: bool = gamepad.is_button_pressed(&mapping.button);
-        let action_key: String = mapping.id.clone();
+        let action_key = ActionKey::new(gamepad, &mapping.id);
 
         if is_pressed && self.evaluate_conditions(gamepad, mapping.conditions.clone()) {
             let just_pressed = {
@@ -222,7 +448,11 @@ impl MappingExecutor {
             };
 
             if should_execute {
-                self.execute_action(&mapping.action, Some(action_key.clone()));
+                self.execute_action(&mapping.action, action_key.clone(), now);
+
+                if just_pressed {
+                    self.play_feedback(&mapping.feedback, &action_key);
+                }
             } else {
                 self.mapping_state.continuous_actions.remove(&action_key);
             }
@@ -231,24 +461,385 @@ impl MappingExecutor {
 
             if self.is_action_active(&action_key) {
                 self.execute_auto_reset_action(&mapping.action);
+                self.cancel_macro(&action_key);
             }
 
             self.stop_action(&action_key);
+            self.mapping_state.last_moves.remove(&action_key);
             self.mapping_state
                 .pressed_buttons
                 .insert(action_key.clone(), false);
         }
     }
 
+    /// Presses and releases the action at the turbo rate while the button is held, or while latched for a toggle turbo.
+    fn process_turbo_button_mapping(
+        &mut self,
+        gamepad: &GamepadState,
+        mapping: &ButtonMapping,
+        turbo: &TurboSettings,
+        now: Instant,
+    ) {
+        let action_key = ActionKey::new(gamepad, &mapping.id);
+        let pressed = gamepad.is_button_pressed(&mapping.button)
+            && self.evaluate_conditions(gamepad, mapping.conditions.clone());
+
+        let state = self
+            .mapping_state
+            .turbos
+            .entry(action_key.clone())
+            .or_default();
+
+        let just_pressed = pressed && !state.was_pressed;
+        state.was_pressed = pressed;
+
+        let running = match turbo.toggle {
+            true => {
+                if just_pressed {
+                    state.latched = !state.latched;
+                }
+                state.latched
+            }
+            false => pressed,
+        };
+
+        let (down_phase, up_phase) = turbo.phases(TICK_INTERVAL);
+
+        // One phase change per pass at most, every press and release is seen by a pass
+        match state.phase_end {
+            _ if !running => state.phase_end = None,
+            None => {
+                state.down = true;
+                state.phase_end = Some(now + down_phase);
+            }
+            Some(phase_end) if now >= phase_end => {
+                state.down = !state.down;
+                state.phase_end = Some(match state.down {
+                    true => phase_end + down_phase,
+                    false => phase_end + up_phase,
+                });
+            }
+            Some(_) => {}
+        }
+
+        let down = running && state.down;
+
+        if down && !self.is_action_active(&action_key) {
+            self.execute_action(&mapping.action, action_key.clone(), now);
+        } else if !down && self.is_action_active(&action_key) {
+            self.execute_auto_reset_action(&mapping.action);
+            self.stop_action(&action_key);
+        }
+
+        if just_pressed {
+            self.play_feedback(&mapping.feedback, &action_key);
+        }
+
+        // The ticker has to keep running to alternate the output
+        match running {
+            true => self.mapping_state.continuous_actions.insert(action_key),
+            false => self.mapping_state.continuous_actions.remove(&action_key),
+        };
+    }
+
+    /// Button mappings with a trigger other than `Press`, the tracker decides when the action starts, stops or fires once.
+    fn process_triggered_button_mapping(
+        &mut self,
+        gamepad: &GamepadState,
+        mapping: &ButtonMapping,
+        trigger: &ButtonTrigger,
+        now: Instant,
+    ) {
+        let action_key = ActionKey::new(gamepad, &mapping.id);
+        let pressed = gamepad.is_button_pressed(&mapping.button)
+            && self.evaluate_conditions(gamepad, mapping.conditions.clone());
+
+        let tap_delay = match trigger {
+            ButtonTrigger::Tap { .. } => self.double_tap_window(gamepad, mapping),
+            _ => None,
+        };
+
+        let outcome = self
+            .mapping_state
+            .button_triggers
+            .entry(action_key.clone())
+            .or_default()
+            .update(trigger, pressed, now, tap_delay);
+
+        match outcome {
+            TriggerOutcome::Start => {
+                self.execute_action(&mapping.action, action_key.clone(), now);
+                self.play_feedback(&mapping.feedback, &action_key);
+            }
+            TriggerOutcome::Stop => {
+                if self.is_action_active(&action_key) {
+                    self.execute_auto_reset_action(&mapping.action);
+                    self.cancel_macro(&action_key);
+                }
+
+                self.stop_action(&action_key);
+                self.mapping_state.last_moves.remove(&action_key);
+            }
+            TriggerOutcome::Pulse => {
+                self.execute_action(&mapping.action, action_key.clone(), now);
+                self.play_feedback(&mapping.feedback, &action_key);
+                self.execute_auto_reset_action(&mapping.action);
+                self.stop_action(&action_key);
+            }
+            TriggerOutcome::Idle => {
+                // A held continuous action keeps moving
+                if self.is_action_active(&action_key) && self.is_action_continuous(&mapping.action)
+                {
+                    self.execute_action(&mapping.action, action_key.clone(), now);
+                }
+            }
+        }
+
+        if !self.is_action_active(&action_key) {
+            self.mapping_state.continuous_actions.remove(&action_key);
+        }
+    }
+
+    /// Window of a double tap on the same button, a tap waits for it to pass before firing.
+    fn double_tap_window(
+        &self,
+        gamepad: &GamepadState,
+        mapping: &ButtonMapping,
+    ) -> Option<Duration> {
+        self.resolve_mappings(gamepad.id())
+            .iter()
+            .filter(|other| other.applies_to(gamepad))
+            .find_map(|other| match other {
+                Mapping::ButtonPressed(other)
+                    if other.id != mapping.id && other.button == mapping.button =>
+                {
+                    match other.trigger {
+                        Some(ButtonTrigger::DoubleTap { ms }) => {
+                            Some(Duration::from_millis(ms as u64))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+    }
+
+    /// Drops the trackers of the mappings the pass skipped (paused, hidden by a layer, removed or no longer
+    /// triggered), releasing what they held. A trigger deadline would otherwise wake the loop over and over.
+    fn drop_skipped_mappings(&mut self) {
+        let skipped_triggers: Vec<ActionKey> = self
+            .mapping_state
+            .button_triggers
+            .keys()
+            .filter(|key| !self.mapping_state.evaluated.contains(key))
+            .cloned()
+            .collect();
+
+        for key in skipped_triggers {
+            if self.is_action_active(&key) {
+                self.release_mapping(&key);
+            }
+
+            self.mapping_state.button_triggers.remove(&key);
+        }
+
+        let skipped_touchpads: Vec<ActionKey> = self
+            .mapping_state
+            .touchpads
+            .keys()
+            .filter(|key| !self.mapping_state.evaluated.contains(key))
+            .cloned()
+            .collect();
+
+        for key in skipped_touchpads {
+            if let Some(tracking) = self.mapping_state.touchpads.remove(&key)
+                && let Some(button) = tracking.pressed_button
+            {
+                let _ = self.sink.mouse_button(&button, enigo::Direction::Release);
+            }
+        }
+    }
+
+    /// Earliest instant a button trigger fires on its own, the loop has to wake up for it.
+    fn next_trigger_deadline(&self) -> Option<Instant> {
+        self.mapping_state
+            .button_triggers
+            .values()
+            .filter_map(|tracker| tracker.deadline())
+            .min()
+    }
+
+    /// Switches the layers of the gamepad from its layer buttons, before its mappings are resolved.
+    fn update_layers(&mut self, gamepad: &GamepadState) {
+        let previous = self
+            .mapping_state
+            .previous_gamepad_states
+            .get(&gamepad.id());
+        let just_pressed: Vec<GamepadButton> = gamepad
+            .pressed_buttons()
+            .into_iter()
+            .filter(|button| !previous.is_some_and(|previous| previous.is_button_pressed(button)))
+            .collect();
+
+        let layer_buttons = self.config.layer_buttons();
+        let state = self.mapping_state.layers.entry(gamepad.id()).or_default();
+
+        for layer in &self.config.layers {
+            let pressed = gamepad.is_button_pressed(&layer.button);
+
+            match layer.mode {
+                LayerMode::Hold => state.set_active(&layer.id, pressed),
+                LayerMode::Toggle if just_pressed.contains(&layer.button) => {
+                    state.set_active(&layer.id, !state.is_active(&layer.id));
+                }
+                // Pressing the button again disarms the layer
+                LayerMode::OneShot if just_pressed.contains(&layer.button) => {
+                    let armed = !state.is_active(&layer.id);
+                    state.set_active(&layer.id, armed);
+
+                    if armed {
+                        state.one_shots.insert(layer.id.clone(), None);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for used_by in state
+            .one_shots
+            .values_mut()
+            .filter(|used_by| used_by.is_none())
+        {
+            *used_by = just_pressed
+                .iter()
+                .find(|button| !layer_buttons.contains(button))
+                .copied();
+        }
+    }
+
+    /// One-shot layers end once the button that used them is released, after its mappings saw the release.
+    fn end_one_shot_layers(&mut self, gamepad: &GamepadState) {
+        let Some(state) = self.mapping_state.layers.get_mut(&gamepad.id()) else {
+            return;
+        };
+
+        let ended: Vec<String> = state
+            .one_shots
+            .iter()
+            .filter(|(_, used_by)| {
+                used_by.is_some_and(|button| !gamepad.is_button_pressed(&button))
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in ended {
+            state.set_active(&id, false);
+        }
+    }
+
+    /// Mappings of the gamepad through its active layers.
+    fn resolve_mappings(&self, gamepad_id: u32) -> Vec<Mapping> {
+        let active_layers = self
+            .mapping_state
+            .layers
+            .get(&gamepad_id)
+            .map(|state| state.active.as_slice())
+            .unwrap_or_default();
+
+        self.config.resolve_mappings(active_layers)
+    }
+
+    /// Keeps the mapping state in step when a layer or profile change alters the resolved mappings.
+    /// Mappings no longer resolved are released and forgotten, they would otherwise stay held. Newly
+    /// resolved button mappings wait for their button to be pressed again rather than firing from a held one.
+    fn sync_resolved_mappings(&mut self, gamepad: &GamepadState, resolved: &[Mapping]) {
+        let visible: HashSet<&str> = resolved.iter().map(Mapping::id).collect();
+        let is_hidden = |key: &ActionKey| {
+            key.gamepad_id == gamepad.id() && !visible.contains(key.mapping_id.as_str())
+        };
+
+        let hidden: Vec<ActionKey> = self
+            .mapping_state
+            .active_actions
+            .keys()
+            .filter(|key| is_hidden(key))
+            .cloned()
+            .collect();
+
+        for key in hidden {
+            self.release_mapping(&key);
+        }
+
+        let state = &mut self.mapping_state;
+        state.continuous_actions.retain(|key| !is_hidden(key));
+        state.pressed_buttons.retain(|key, _| !is_hidden(key));
+        state.button_triggers.retain(|key, _| !is_hidden(key));
+        state.turbos.retain(|key, _| !is_hidden(key));
+
+        if let Some(previous) = state.resolved.get(&gamepad.id()) {
+            let held: Vec<&ButtonMapping> = resolved
+                .iter()
+                .filter_map(|mapping| match mapping {
+                    Mapping::ButtonPressed(mapping) => Some(mapping),
+                    _ => None,
+                })
+                .filter(|mapping| previous.iter().all(|other| other.id() != mapping.id))
+                .filter(|mapping| gamepad.is_button_pressed(&mapping.button))
+                .collect();
+
+            for mapping in held {
+                let action_key = ActionKey::new(gamepad, &mapping.id);
+                state.pressed_buttons.insert(action_key.clone(), true);
+                state.turbos.insert(
+                    action_key,
+                    TurboState {
+                        was_pressed: true,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        state.resolved.insert(gamepad.id(), resolved.to_vec());
+    }
+
+    /// Releases what the mapping was holding and forgets it was active.
+    fn release_mapping(&mut self, key: &ActionKey) {
+        // The mapping may be gone from the config already, after a profile switch
+        let action = self
+            .mapping_state
+            .resolved
+            .get(&key.gamepad_id)
+            .into_iter()
+            .flatten()
+            .chain(self.config.all_mappings())
+            .find(|mapping| mapping.id() == key.mapping_id)
+            .and_then(Mapping::action)
+            .cloned();
+
+        if let Some(action) = action {
+            self.execute_auto_reset_action(&action);
+        }
+
+        self.cancel_macro(key);
+        self.stop_action(key);
+    }
+
     fn process_axis_trigger_mapping(
         &mut self,
         gamepad: &GamepadState,
         mapping: &AxisTriggerMapping,
+        now: Instant,
     ) {
-        let axis_value = gamepad.axis().get(&mapping.axis).copied().unwrap_or(0);
-        let normalized_value = axis_value.abs() as f32 / 32767.0;
+        // Same calibration, deadzone and curve as the sticks, one axis on its own
+        let (normalized_value, _) = DeadzoneSettings::with_inner(self.config.deadzone)
+            .apply(gamepad.get_normalized_axis_value(&mapping.axis).abs(), 0.0);
+        let normalized_value = match &mapping.curve {
+            Some(curve) => curve.apply(normalized_value),
+            None => normalized_value,
+        };
 
-        let action_key = mapping.id.clone();
+        let action_key = ActionKey::new(gamepad, &mapping.id);
 
         let threshold_met = normalized_value >= mapping.threshold;
         let condition_met = self.evaluate_conditions(gamepad, mapping.conditions.clone());
@@ -258,7 +849,8 @@ impl MappingExecutor {
         }
 
         if threshold_met && condition_met && !self.is_action_active(&action_key) {
-            return self.execute_action(&mapping.action, Some(action_key.clone()));
+            self.execute_action(&mapping.action, action_key.clone(), now);
+            return self.play_feedback(&mapping.feedback, &action_key);
         }
 
         self.mapping_state.continuous_actions.remove(&action_key);
@@ -268,14 +860,21 @@ impl MappingExecutor {
         }
 
         self.execute_auto_reset_action(&mapping.action);
+        self.cancel_macro(&action_key);
 
         self.stop_action(&action_key);
     }
 
-    fn process_axis_stick_mapping(&mut self, gamepad: &GamepadState, mapping: &AxisStickMapping) {
+    fn process_axis_stick_mapping(
+        &mut self,
+        gamepad: &GamepadState,
+        mapping: &AxisStickMapping,
+        now: Instant,
+    ) {
         let (axis_x, axis_y) = match mapping.stick {
             StickType::LeftStick => (GamepadAxis::LeftX, GamepadAxis::LeftY),
             StickType::RightStick => (GamepadAxis::RightX, GamepadAxis::RightY),
+            StickType::Gyro => return self.process_gyro_mapping(gamepad, mapping),
         };
 
         let ignore_deadzone = match mapping.action.clone() {
@@ -283,16 +882,33 @@ impl MappingExecutor {
             _ => false,
         };
 
-        let is_over_deadzone = ignore_deadzone
-            || self.stick_is_over_deadzone(
-                gamepad.axis().get(&axis_x).copied().unwrap_or(0),
-                gamepad.axis().get(&axis_y).copied().unwrap_or(0),
-            );
+        let raw_x = gamepad.get_normalized_axis_value(&axis_x);
+        let raw_y = gamepad.get_normalized_axis_value(&axis_y);
+
+        // Shared by every stick action, values are rescaled to the full range then reshaped by the curve
+        let (x_value, y_value) = match ignore_deadzone {
+            true => (raw_x, raw_y),
+            false => {
+                let (x, y) = self
+                    .config
+                    .stick_deadzone(&mapping.stick)
+                    .apply(raw_x, raw_y);
+
+                match &mapping.curve {
+                    Some(curve) => curve.apply_to_stick(x, y),
+                    None => (x, y),
+                }
+            }
+        };
+
+        let is_over_deadzone = ignore_deadzone || x_value != 0.0 || y_value != 0.0;
+
+        let action_key = ActionKey::new(gamepad, &mapping.id);
 
         if !self.evaluate_conditions(gamepad, mapping.conditions.clone()) || !is_over_deadzone {
-            self.mapping_state
-                .continuous_actions
-                .remove(&mapping.id.clone());
+            self.mapping_state.continuous_actions.remove(&action_key);
+            self.mapping_state.scroll_accumulators.remove(&action_key);
+            self.mapping_state.last_moves.remove(&action_key);
             return;
         }
 
@@ -300,33 +916,175 @@ impl MappingExecutor {
             return;
         }
 
-        let x_value = gamepad.get_normalized_axis_value(&axis_x);
-        let y_value = gamepad.get_normalized_axis_value(&axis_y);
-
         match &mapping.action {
             Action::MouseMoveStick { .. } => {
-                self.execute_mouse_move_stick(x_value, y_value, &mapping.action);
+                self.execute_mouse_move_stick(x_value, y_value, &mapping.action, &action_key, now);
             }
             Action::ScrollStick { .. } => {
-                self.execute_scroll_stick(x_value, y_value, &mapping.action);
+                self.execute_scroll_stick(x_value, y_value, &mapping.action, &action_key, now);
             }
             _ => {}
         }
     }
 
+    fn process_gyro_mapping(&mut self, gamepad: &GamepadState, mapping: &AxisStickMapping) {
+        let settings = match &mapping.action {
+            Action::MouseMoveGyro { settings } => settings,
+            _ => return,
+        };
+
+        let action_key = ActionKey::new(gamepad, &mapping.id);
+        let gyro_enabled = self.update_gyro_ratchet(gamepad, &action_key, &settings.ratchet);
+
+        let reading = match gamepad.motion().gyro {
+            Some(reading) => reading,
+            None => return,
+        };
+
+        let previous_timestamp = self
+            .mapping_state
+            .gyro_timestamps
+            .insert(action_key.clone(), reading.timestamp);
+
+        if !gyro_enabled
+            || !self.evaluate_conditions(gamepad, mapping.conditions.clone())
+            || !MAPPING_ACTIVE.load(Ordering::Relaxed)
+        {
+            return;
+        }
+
+        let previous_timestamp = match previous_timestamp {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
+
+        let (delta_x, delta_y) = gyro_mouse_delta(&reading, previous_timestamp, settings);
+
+        self.move_mouse_relative(&action_key, delta_x, delta_y);
+    }
+
+    fn update_gyro_ratchet(
+        &mut self,
+        gamepad: &GamepadState,
+        action_key: &ActionKey,
+        ratchet: &Option<GyroRatchet>,
+    ) -> bool {
+        let ratchet = match ratchet {
+            Some(ratchet) => ratchet,
+            None => return true,
+        };
+
+        let is_pressed = gamepad.is_button_pressed(&ratchet.button);
+        let state = self
+            .mapping_state
+            .gyro_ratchets
+            .entry(action_key.clone())
+            .or_default();
+
+        if is_pressed && !state.was_pressed {
+            state.latched = !state.latched;
+        }
+
+        state.was_pressed = is_pressed;
+
+        match ratchet.mode {
+            RatchetMode::Hold => is_pressed,
+            RatchetMode::Toggle => state.latched,
+        }
+    }
+
+    fn process_touchpad_mapping(
+        &mut self,
+        gamepad: &GamepadState,
+        mapping: &TouchpadMapping,
+        now: Instant,
+    ) {
+        const TAP_MAX_DURATION: Duration = Duration::from_millis(200);
+        const TAP_MAX_TRAVEL: f32 = 0.03;
+
+        let action_key = ActionKey::new(gamepad, &mapping.id);
+        let mut tracking = self
+            .mapping_state
+            .touchpads
+            .remove(&action_key)
+            .unwrap_or_default();
+
+        let fingers = gamepad.touchpad_fingers().to_vec();
+        let enabled = MAPPING_ACTIVE.load(Ordering::Relaxed)
+            && self.evaluate_conditions(gamepad, mapping.conditions.clone());
+
+        if tracking.previous_fingers.is_empty() && !fingers.is_empty() {
+            tracking.gesture_start = Some(now);
+            tracking.gesture_travel = 0.0;
+            tracking.gesture_max_fingers = 0;
+        }
+
+        tracking.gesture_max_fingers = tracking.gesture_max_fingers.max(fingers.len());
+
+        if let Some(finger) = fingers.first() {
+            tracking.last_x = finger.x;
+        }
+
+        let (delta_x, delta_y) = touchpad_delta(&tracking.previous_fingers, &fingers);
+        tracking.gesture_travel += delta_x.hypot(delta_y);
+
+        if enabled && fingers.len() == 1 {
+            let pixels_per_width = mapping.sensitivity as f32 * 10.0;
+            self.move_mouse_relative(
+                &action_key,
+                delta_x * pixels_per_width,
+                delta_y * pixels_per_width,
+            );
+        } else if enabled && fingers.len() == 2 {
+            // Natural scrolling, the content follows the fingers
+            let speed = mapping.scroll_speed as f32;
+            self.accumulate_scroll(&action_key, -delta_x * speed, -delta_y * speed);
+        }
+
+        if fingers.len() < 2 {
+            self.mapping_state.scroll_accumulators.remove(&action_key);
+        }
+
+        let gesture_ended = !tracking.previous_fingers.is_empty() && fingers.is_empty();
+        let is_tap = tracking
+            .gesture_start
+            .is_some_and(|start| now.duration_since(start) <= TAP_MAX_DURATION)
+            && tracking.gesture_travel <= TAP_MAX_TRAVEL;
+
+        if enabled && mapping.tap_to_click && gesture_ended && is_tap {
+            let button = match tracking.gesture_max_fingers {
+                1 => touchpad_zone_button(mapping, tracking.last_x),
+                _ => MouseButton::Right,
+            };
+
+            let _ = self.sink.mouse_button(&button, enigo::Direction::Click);
+        }
+
+        let is_clicked = gamepad.is_button_pressed(&GamepadButton::Touchpad);
+
+        if enabled && is_clicked && tracking.pressed_button.is_none() {
+            let button = touchpad_zone_button(mapping, tracking.last_x);
+            let _ = self.sink.mouse_button(&button, enigo::Direction::Press);
+            tracking.pressed_button = Some(button);
+        } else if (!is_clicked || !enabled)
+            && let Some(button) = tracking.pressed_button.take()
+        {
+            let _ = self.sink.mouse_button(&button, enigo::Direction::Release);
+        }
+
+        tracking.previous_fingers = fingers;
+
+        self.mapping_state.touchpads.insert(action_key, tracking);
+    }
+
     fn execute_auto_reset_action(&mut self, original_action: &Action) {
         match original_action {
             Action::MouseClick { button } => {
-                let enigo_button = match button {
-                    MouseButton::Left => EnigoButton::Left,
-                    MouseButton::Right => EnigoButton::Right,
-                    MouseButton::Middle => EnigoButton::Middle,
-                };
-                let _ = self.enigo.button(enigo_button, enigo::Direction::Release);
+                let _ = self.sink.mouse_button(button, enigo::Direction::Release);
             }
             Action::PressKeys { keys } => {
                 for key in keys.iter().rev() {
-                    let _ = virtual_keyboard::release_key(key, &mut self.enigo);
+                    let _ = self.sink.release_key(key);
                 }
             }
             _ => {}
@@ -366,15 +1124,22 @@ impl MappingExecutor {
         }
     }
 
-    fn execute_action(&mut self, action: &Action, action_key: Option<String>) {
-        self.perform_action(action);
+    fn execute_action(&mut self, action: &Action, action_key: ActionKey, now: Instant) {
+        self.perform_action(action, &action_key, now);
+        self.mark_action_active(action_key);
+    }
 
-        if let Some(key) = action_key {
-            self.mark_action_active(key);
+    /// Rumbles the gamepad that triggered the mapping, even when mapping is paused.
+    fn play_feedback(&self, feedback: &Option<Rumble>, action_key: &ActionKey) {
+        if let Some(rumble) = feedback {
+            send_backend_command(BackendCommand::Rumble {
+                id: action_key.gamepad_id,
+                rumble: rumble.clone(),
+            });
         }
     }
 
-    fn perform_action(&mut self, action: &Action) {
+    fn perform_action(&mut self, action: &Action, action_key: &ActionKey, now: Instant) {
         if !MAPPING_ACTIVE.load(Ordering::Relaxed) && !matches!(action, Action::ToogleMappingActive)
         {
             return;
@@ -383,69 +1148,80 @@ impl MappingExecutor {
         match action {
             Action::PressKeys { keys } => {
                 for key in keys.iter() {
-                    let _ = virtual_keyboard::press_key(key, &mut self.enigo, false);
+                    let _ = self.sink.press_key(key);
                 }
             }
             Action::WriteText { text } => {
-                let _ = self.enigo.text(text);
+                let _ = self.sink.text(text);
             }
             Action::MouseClick { button } => {
-                let enigo_button = match button {
-                    MouseButton::Left => EnigoButton::Left,
-                    MouseButton::Right => EnigoButton::Right,
-                    MouseButton::Middle => EnigoButton::Middle,
-                };
-                let _ = self.enigo.button(enigo_button, enigo::Direction::Press);
+                let _ = self.sink.mouse_button(button, enigo::Direction::Press);
             }
             Action::MouseMoveDirection { direction, speed } => {
-                self.execute_mouse_move_direction(direction, *speed);
+                self.execute_mouse_move_direction(direction, *speed, action_key, now);
             }
             Action::ScrollDirection { direction, speed } => {
-                self.execute_scroll_direction(direction, *speed);
-            }
-            Action::OpenWebsite { url } => {
-                let _ = self.app.opener().open_url(url, None::<&str>);
-            }
-            Action::OpenFile { path } => {
-                let str_path = path.to_str().unwrap();
-                let _ = self.app.opener().open_path(str_path, None::<&str>);
-            }
-            Action::ToogleMappingActive => {
-                let set = !MAPPING_ACTIVE.load(Ordering::Relaxed);
-                set_mapping_active(set);
+                self.execute_scroll_direction(direction, *speed, action_key, now);
             }
-            Action::ToogleVirtualKeyboard => {
-                let _ = virtual_keyboard::toogle_vk_window(&get_app_handle());
+            Action::Macro { sequence } => {
+                // Pressing again while the macro runs doesn't start a second one
+                if let Some(running) = self.mapping_state.macros.get(action_key)
+                    && running.is_running()
+                {
+                    return;
+                }
+
+                let running = spawn_macro(sequence, action_key.gamepad_id);
+                self.mapping_state
+                    .macros
+                    .insert(action_key.clone(), running);
             }
-            _ => {}
+            action => perform_system_action(action, action_key.gamepad_id),
         }
     }
 
-    fn execute_mouse_move_stick(&mut self, x_value: f32, y_value: f32, action: &Action) {
+    /// Stops the macro started by this mapping if it should end with the input.
+    fn cancel_macro(&mut self, action_key: &ActionKey) {
+        if let Some(running) = self.mapping_state.macros.get(action_key)
+            && running.cancel_on_release()
+        {
+            running.cancel();
+        }
+    }
+
+    /// Stops every running macro, when mapping is paused or the profile changes.
+    fn cancel_all_macros(&mut self) {
+        for (_, running) in self.mapping_state.macros.drain() {
+            running.cancel();
+        }
+    }
+
+    fn execute_mouse_move_stick(
+        &mut self,
+        x_value: f32,
+        y_value: f32,
+        action: &Action,
+        action_key: &ActionKey,
+        now: Instant,
+    ) {
         let (mouse_mode, speed) = match action {
             Action::MouseMoveStick { mode, speed } => (mode.to_owned(), speed),
             _ => (MouseMoveMode::Relative, &0),
         };
 
-        let base_speed = *speed as f32;
-
-        let velocity_x = x_value * base_speed;
-        let velocity_y = y_value * base_speed;
-
-        let delta_x = velocity_x as i32;
-        let delta_y = velocity_y as i32;
-
-        if (delta_x != 0 || delta_y != 0) && mouse_mode == MouseMoveMode::Relative {
-            let _ = self.enigo.move_mouse(delta_x, delta_y, Coordinate::Rel);
+        if mouse_mode == MouseMoveMode::Relative {
+            let distance = *speed as f32 * self.move_elapsed(action_key, now);
+            self.move_mouse_relative(action_key, x_value * distance, y_value * distance);
         } else if mouse_mode == MouseMoveMode::Absolute {
-            let mouse_pos = self.app.cursor_position();
+            let app = get_app_handle();
+            let mouse_pos = app.cursor_position();
 
             if mouse_pos.is_err() {
                 return;
             }
 
             let mouse_pos = mouse_pos.unwrap();
-            let screen_size = self.app.monitor_from_point(mouse_pos.x, mouse_pos.y);
+            let screen_size = app.monitor_from_point(mouse_pos.x, mouse_pos.y);
 
             if screen_size.is_err() {
                 return;
@@ -458,85 +1234,124 @@ impl MappingExecutor {
             let target_y = (((y_value * 1.314159 + 1.0) / 2.0) * screen_size.size().height as f32)
                 .round() as i32;
 
-            let _ = self.enigo.move_mouse(target_x, target_y, Coordinate::Abs);
+            let _ = self.sink.move_mouse(target_x, target_y, Coordinate::Abs);
+        }
+    }
+
+    /// Moves the cursor by a fractional delta, the sub-pixel part is kept per mapping for the next move.
+    fn move_mouse_relative(&mut self, action_key: &ActionKey, delta_x: f32, delta_y: f32) {
+        let remainder = self
+            .mapping_state
+            .mouse_remainders
+            .entry(action_key.clone())
+            .or_insert((0.0, 0.0));
+
+        let total_x = remainder.0 + delta_x;
+        let total_y = remainder.1 + delta_y;
+
+        let move_x = total_x.trunc() as i32;
+        let move_y = total_y.trunc() as i32;
+
+        *remainder = (total_x - move_x as f32, total_y - move_y as f32);
+
+        if move_x != 0 || move_y != 0 {
+            let _ = self.sink.move_mouse(move_x, move_y, Coordinate::Rel);
         }
     }
 
-    fn execute_scroll_stick(&mut self, x_value: f32, y_value: f32, action: &Action) {
+    fn execute_scroll_stick(
+        &mut self,
+        x_value: f32,
+        y_value: f32,
+        action: &Action,
+        action_key: &ActionKey,
+        now: Instant,
+    ) {
         let speed = match action {
-            Action::ScrollStick { speed } => *speed as f32 / 100.0,
+            Action::ScrollStick { speed } => *speed as f32 * self.move_elapsed(action_key, now),
             _ => 0.0,
         };
 
         let scroll_velocity_x = x_value * speed;
         let scroll_velocity_y = y_value * speed;
 
-        self.scroll_accumulator_x += scroll_velocity_x;
-        self.scroll_accumulator_y += scroll_velocity_y;
-
-        if self.scroll_accumulator_y.abs() >= 1.0 {
-            let scroll_amount = self.scroll_accumulator_y as i32;
-            self.scroll_accumulator_y -= scroll_amount as f32;
-
-            if scroll_amount != 0 {
-                let _ = self.enigo.scroll(scroll_amount, Axis::Vertical);
-            }
-        }
+        self.accumulate_scroll(action_key, scroll_velocity_x, scroll_velocity_y);
+    }
 
-        if self.scroll_accumulator_x.abs() >= 1.0 {
-            let scroll_amount = self.scroll_accumulator_x as i32;
-            self.scroll_accumulator_x -= scroll_amount as f32;
+    fn execute_mouse_move_direction(
+        &mut self,
+        direction: &Direction,
+        speed: u16,
+        action_key: &ActionKey,
+        now: Instant,
+    ) {
+        let distance = speed as f32 * self.move_elapsed(action_key, now);
 
-            if scroll_amount != 0 {
-                let _ = self.enigo.scroll(scroll_amount, Axis::Horizontal);
-            }
-        }
+        let (dx, dy) = match direction {
+            Direction::Up => (0.0, -distance),
+            Direction::Down => (0.0, distance),
+            Direction::Left => (-distance, 0.0),
+            Direction::Right => (distance, 0.0),
+        };
 
-        if x_value.abs() < self.config.deadzone && y_value.abs() < self.config.deadzone {
-            self.scroll_accumulator_x = 0.0;
-            self.scroll_accumulator_y = 0.0;
-        }
+        self.move_mouse_relative(action_key, dx, dy);
     }
 
-    fn execute_mouse_move_direction(&mut self, direction: &Direction, speed: u8) {
-        let speed_accumulator = speed as i32;
+    fn execute_scroll_direction(
+        &mut self,
+        direction: &Direction,
+        speed: u8,
+        action_key: &ActionKey,
+        now: Instant,
+    ) {
+        let speed = speed as f32 * self.move_elapsed(action_key, now);
 
-        let (dx, dy) = match direction {
-            Direction::Up => (0, -(speed_accumulator)),
-            Direction::Down => (0, speed_accumulator),
-            Direction::Left => (-(speed_accumulator), 0),
-            Direction::Right => (speed_accumulator, 0),
+        let (delta_x, delta_y) = match direction {
+            Direction::Up => (0.0, -speed),
+            Direction::Down => (0.0, speed),
+            Direction::Left => (-speed, 0.0),
+            Direction::Right => (speed, 0.0),
         };
 
-        let _ = self.enigo.move_mouse(dx, dy, Coordinate::Rel);
+        self.accumulate_scroll(action_key, delta_x, delta_y);
     }
 
-    fn execute_scroll_direction(&mut self, direction: &Direction, speed: u8) {
-        let speed = speed as f32 / 100.0;
+    /// Seconds between the previous move of this mapping and `now`, the first move of a gesture counts as one tick.
+    fn move_elapsed(&mut self, action_key: &ActionKey, now: Instant) -> f32 {
+        let previous = self
+            .mapping_state
+            .last_moves
+            .insert(action_key.clone(), now);
+
+        previous
+            .map(|previous| now.duration_since(previous).min(MAX_MOVE_STEP))
+            .unwrap_or(TICK_INTERVAL)
+            .as_secs_f32()
+    }
 
-        match direction {
-            Direction::Up => self.scroll_accumulator_y -= speed,
-            Direction::Down => self.scroll_accumulator_y += speed,
-            Direction::Left => self.scroll_accumulator_x -= speed,
-            Direction::Right => self.scroll_accumulator_x += speed,
-        }
+    /// Adds a fractional scroll to the mapping accumulator and scrolls once it reaches a full step.
+    fn accumulate_scroll(&mut self, action_key: &ActionKey, delta_x: f32, delta_y: f32) {
+        let accumulator = self
+            .mapping_state
+            .scroll_accumulators
+            .entry(action_key.clone())
+            .or_insert((0.0, 0.0));
 
-        if self.scroll_accumulator_y.abs() >= 1.0 {
-            let scroll_amount = self.scroll_accumulator_y as i32;
-            self.scroll_accumulator_y -= scroll_amount as f32;
+        accumulator.0 += delta_x;
+        accumulator.1 += delta_y;
 
-            if scroll_amount != 0 {
-                let _ = self.enigo.scroll(scroll_amount, Axis::Vertical);
-            }
-        }
+        let scroll_x = accumulator.0 as i32;
+        let scroll_y = accumulator.1 as i32;
 
-        if self.scroll_accumulator_x.abs() >= 1.0 {
-            let scroll_amount = self.scroll_accumulator_x as i32;
-            self.scroll_accumulator_x -= scroll_amount as f32;
+        accumulator.0 -= scroll_x as f32;
+        accumulator.1 -= scroll_y as f32;
 
-            if scroll_amount != 0 {
-                let _ = self.enigo.scroll(scroll_amount, Axis::Horizontal);
-            }
+        if scroll_y != 0 {
+            let _ = self.sink.scroll(scroll_y, Axis::Vertical);
+        }
+
+        if scroll_x != 0 {
+            let _ = self.sink.scroll(scroll_x, Axis::Horizontal);
         }
     }
 
@@ -563,28 +1378,1105 @@ impl MappingExecutor {
         )
     }
 
-    fn stick_is_over_deadzone(&self, x_value: i16, y_value: i16) -> bool {
-        let normalized_x = x_value.abs() as f32 / 32767.0;
-        let normalized_y = y_value.abs() as f32 / 32767.0;
-
-        normalized_x > self.config.deadzone || normalized_y > self.config.deadzone
-    }
-
-    fn is_action_active(&self, action_key: &str) -> bool {
+    fn is_action_active(&self, action_key: &ActionKey) -> bool {
         self.mapping_state.active_actions.contains_key(action_key)
     }
 
-    fn mark_action_active(&mut self, action_key: String) {
+    fn mark_action_active(&mut self, action_key: ActionKey) {
         self.mapping_state
             .active_actions
             .insert(action_key, Instant::now());
     }
 
-    fn stop_action(&mut self, action_key: &str) {
+    fn stop_action(&mut self, action_key: &ActionKey) {
         self.mapping_state.active_actions.remove(action_key);
     }
+
+    /// Releases what disconnected gamepads were still holding and drops their state.
+    fn release_disconnected_gamepads(&mut self, connected: &HashSet<u32>) {
+        let stale_keys: Vec<ActionKey> = self
+            .mapping_state
+            .active_actions
+            .keys()
+            .filter(|key| !connected.contains(&key.gamepad_id))
+            .cloned()
+            .collect();
+
+        for key in stale_keys {
+            self.release_mapping(&key);
+        }
+
+        let state = &mut self.mapping_state;
+        state
+            .continuous_actions
+            .retain(|key| connected.contains(&key.gamepad_id));
+        state
+            .pressed_buttons
+            .retain(|key, _| connected.contains(&key.gamepad_id));
+        state
+            .previous_gamepad_states
+            .retain(|id, _| connected.contains(id));
+        state
+            .gyro_timestamps
+            .retain(|key, _| connected.contains(&key.gamepad_id));
+        state
+            .gyro_ratchets
+            .retain(|key, _| connected.contains(&key.gamepad_id));
+        state
+            .mouse_remainders
+            .retain(|key, _| connected.contains(&key.gamepad_id));
+        state
+            .scroll_accumulators
+            .retain(|key, _| connected.contains(&key.gamepad_id));
+        state
+            .last_moves
+            .retain(|key, _| connected.contains(&key.gamepad_id));
+        state
+            .touchpads
+            .retain(|key, _| connected.contains(&key.gamepad_id));
+        state
+            .button_triggers
+            .retain(|key, _| connected.contains(&key.gamepad_id));
+        state
+            .turbos
+            .retain(|key, _| connected.contains(&key.gamepad_id));
+        state.layers.retain(|id, _| connected.contains(id));
+        state.resolved.retain(|id, _| connected.contains(id));
+        state.macros.retain(|key, running| {
+            let connected = connected.contains(&key.gamepad_id);
+
+            if !connected && running.cancel_on_release() {
+                running.cancel();
+            }
+
+            connected
+        });
+    }
+}
+
+/// Runs the actions that don't go through the input sink, shared with the macro steps.
+pub fn perform_system_action(action: &Action, gamepad_id: u32) {
+    match action {
+        Action::OpenWebsite { url } => {
+            let _ = get_app_handle().opener().open_url(url, None::<&str>);
+        }
+        Action::OpenFile { path } => {
+            if let Some(path) = path.to_str() {
+                let _ = get_app_handle().opener().open_path(path, None::<&str>);
+            }
+        }
+        Action::Rumble { rumble } => {
+            send_backend_command(BackendCommand::Rumble {
+                id: gamepad_id,
+                rumble: rumble.clone(),
+            });
+        }
+        Action::SetLedColor { color } => {
+            send_backend_command(BackendCommand::SetLed {
+                id: gamepad_id,
+                color: *color,
+            });
+        }
+        Action::ToogleMappingActive => set_mapping_active(!get_mapping_active()),
+        Action::ToogleVirtualKeyboard => {
+            let _ = virtual_keyboard::toogle_vk_window(&get_app_handle());
+        }
+        Action::SwitchProfile { profile } => {
+            if let Err(e) = ConfigService::get_instance().activate_profile(profile) {
+                eprintln!("Unable to switch profile: {}", e);
+            }
+        }
+        Action::NextProfile => ConfigService::get_instance().cycle_profile(1),
+        Action::PreviousProfile => ConfigService::get_instance().cycle_profile(-1),
+        _ => {}
+    }
+}
+
+fn touchpad_zone_button(mapping: &TouchpadMapping, x: f32) -> MouseButton {
+    match mapping.right_click_zone {
+        Some(zone) if x * 100.0 >= zone as f32 => MouseButton::Right,
+        _ => MouseButton::Left,
+    }
+}
+
+/// Average movement of the fingers present in both frames, in normalized touchpad units.
+pub fn touchpad_delta(previous: &[TouchpadFinger], current: &[TouchpadFinger]) -> (f32, f32) {
+    if previous.len() != current.len() {
+        return (0.0, 0.0);
+    }
+
+    let moves: Vec<(f32, f32)> = current
+        .iter()
+        .filter_map(|finger| {
+            previous
+                .iter()
+                .find(|previous| previous.finger == finger.finger)
+                .map(|previous| (finger.x - previous.x, finger.y - previous.y))
+        })
+        .collect();
+
+    if moves.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let count = moves.len() as f32;
+    let (sum_x, sum_y) = moves
+        .iter()
+        .fold((0.0, 0.0), |(acc_x, acc_y), (x, y)| (acc_x + x, acc_y + y));
+
+    (sum_x / count, sum_y / count)
+}
+
+/// Converts a gyro sample into a cursor delta in pixels, integrating the angular velocity since the previous sample.
+pub fn gyro_mouse_delta(
+    reading: &SensorReading,
+    previous_timestamp: u64,
+    settings: &GyroMouseSettings,
+) -> (f32, f32) {
+    const MAX_SAMPLE_GAP_NS: u64 = 100_000_000;
+
+    let elapsed_ns = reading.timestamp.saturating_sub(previous_timestamp);
+
+    if elapsed_ns == 0 || elapsed_ns > MAX_SAMPLE_GAP_NS {
+        return (0.0, 0.0);
+    }
+
+    let elapsed_secs = elapsed_ns as f32 / 1_000_000_000.0;
+
+    // SDL reports radians per second: x is pitch, y is yaw and z is roll
+    let pitch = reading.x.to_degrees();
+    let horizontal = match settings.horizontal_axis {
+        GyroAxis::Yaw => reading.y.to_degrees(),
+        GyroAxis::Roll => reading.z.to_degrees(),
+    };
+
+    if horizontal.hypot(pitch) < settings.deadzone as f32 {
+        return (0.0, 0.0);
+    }
+
+    let delta_x = -horizontal * elapsed_secs * settings.sensitivity_x as f32;
+    let delta_y = -pitch * elapsed_secs * settings.sensitivity_y as f32;
+
+    (delta_x, delta_y)
 }
 
 pub async fn start_mapping_system() -> Result<(), Box<dyn std::error::Error>> {
     MappingExecutor::start_mapping_loop().await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{
+            calibration::{AxisCalibration, ControllerCalibration},
+            config::MappingLayer,
+            mapping::button_mapping,
+        },
+        services::input_sink::{RecordingSink, SinkEvent},
+    };
+
+    fn executor(mappings: Vec<Mapping>) -> (MappingExecutor, RecordingSink) {
+        let sink = RecordingSink::default();
+        let mut executor = MappingExecutor::with_sink(Box::new(sink.clone()));
+        executor.config.mappings = mappings;
+        (executor, sink)
+    }
+
+    fn press_key(key: &str) -> Action {
+        Action::PressKeys {
+            keys: vec![key.to_string()],
+        }
+    }
+
+    fn key_mapping(id: &str, button: GamepadButton, key: &str) -> Mapping {
+        Mapping::ButtonPressed(button_mapping(id, button, press_key(key)))
+    }
+
+    fn gamepads(ids: &[u32]) -> HashMap<u32, GamepadState> {
+        ids.iter()
+            .map(|id| (*id, GamepadState::new(*id, format!("Pad {}", id))))
+            .collect()
+    }
+
+    fn button_event(
+        gamepad_id: u32,
+        button: GamepadButton,
+        pressed: bool,
+        at: Instant,
+    ) -> InputEvent {
+        let kind = match pressed {
+            true => InputEventKind::ButtonDown { button },
+            false => InputEventKind::ButtonUp { button },
+        };
+
+        InputEvent::new(gamepad_id, kind, at)
+    }
+
+    fn key(gamepad_id: u32, mapping_id: &str) -> ActionKey {
+        ActionKey {
+            gamepad_id,
+            mapping_id: mapping_id.to_string(),
+        }
+    }
+
+    /// Executor with `A` typing "a", and a layer on the left shoulder where `A` types "b".
+    fn layered_executor(mode: LayerMode) -> (MappingExecutor, RecordingSink) {
+        let (mut executor, sink) = executor(vec![
+            key_mapping("a", GamepadButton::A, "a"),
+            key_mapping("shoulder", GamepadButton::LeftShoulder, "l"),
+        ]);
+        executor.config.layers = vec![MappingLayer {
+            id: "fn".to_string(),
+            name: "Fn".to_string(),
+            button: GamepadButton::LeftShoulder,
+            mode,
+            mappings: vec![key_mapping("b", GamepadButton::A, "b")],
+        }];
+        (executor, sink)
+    }
+
+    /// Runs a pass with `button` switched to `pressed`, one tick after the previous one.
+    fn press(
+        executor: &mut MappingExecutor,
+        pads: &mut HashMap<u32, GamepadState>,
+        button: GamepadButton,
+        pressed: bool,
+        at: &mut Instant,
+    ) {
+        *at += TICK_INTERVAL;
+        pads.get_mut(&1).unwrap().set_button(button, pressed);
+        executor.process_gamepads(std::iter::empty(), pads, &None, *at);
+    }
+
+    /// Presses and releases `A`, returning the keys it typed.
+    fn tap_a(
+        executor: &mut MappingExecutor,
+        sink: &RecordingSink,
+        pads: &mut HashMap<u32, GamepadState>,
+        at: &mut Instant,
+    ) -> Vec<SinkEvent> {
+        let before = sink.events().len();
+        press(executor, pads, GamepadButton::A, true, at);
+        press(executor, pads, GamepadButton::A, false, at);
+        sink.events()[before..].to_vec()
+    }
+
+    fn typed(key: &str) -> Vec<SinkEvent> {
+        vec![
+            SinkEvent::KeyDown(key.to_string()),
+            SinkEvent::KeyUp(key.to_string()),
+        ]
+    }
+
+    #[test]
+    fn hold_layers_apply_while_their_button_is_held() {
+        let (mut executor, sink) = layered_executor(LayerMode::Hold);
+        let mut pads = gamepads(&[1]);
+        let mut at = Instant::now();
+        executor.process_gamepads(std::iter::empty(), &pads, &None, at);
+
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            true,
+            &mut at,
+        );
+        assert_eq!(tap_a(&mut executor, &sink, &mut pads, &mut at), typed("b"));
+
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            false,
+            &mut at,
+        );
+        assert_eq!(tap_a(&mut executor, &sink, &mut pads, &mut at), typed("a"));
+
+        // The layer button no longer fires its own mapping
+        assert!(!sink.events().contains(&SinkEvent::KeyDown("l".to_string())));
+    }
+
+    #[test]
+    fn toggle_layers_switch_on_each_press() {
+        let (mut executor, sink) = layered_executor(LayerMode::Toggle);
+        let mut pads = gamepads(&[1]);
+        let mut at = Instant::now();
+        executor.process_gamepads(std::iter::empty(), &pads, &None, at);
+
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            true,
+            &mut at,
+        );
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            false,
+            &mut at,
+        );
+        assert_eq!(tap_a(&mut executor, &sink, &mut pads, &mut at), typed("b"));
+        assert_eq!(tap_a(&mut executor, &sink, &mut pads, &mut at), typed("b"));
+
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            true,
+            &mut at,
+        );
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            false,
+            &mut at,
+        );
+        assert_eq!(tap_a(&mut executor, &sink, &mut pads, &mut at), typed("a"));
+    }
+
+    #[test]
+    fn one_shot_layers_end_when_the_next_button_is_released() {
+        let (mut executor, sink) = layered_executor(LayerMode::OneShot);
+        let mut pads = gamepads(&[1]);
+        let mut at = Instant::now();
+        executor.process_gamepads(std::iter::empty(), &pads, &None, at);
+
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            true,
+            &mut at,
+        );
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            false,
+            &mut at,
+        );
+
+        // The layer stays on while the button that used it is held
+        press(&mut executor, &mut pads, GamepadButton::A, true, &mut at);
+        press(&mut executor, &mut pads, GamepadButton::B, true, &mut at);
+        assert_eq!(sink.events(), vec![SinkEvent::KeyDown("b".to_string())]);
+        press(&mut executor, &mut pads, GamepadButton::A, false, &mut at);
+        press(&mut executor, &mut pads, GamepadButton::B, false, &mut at);
+        assert_eq!(sink.events(), typed("b"));
+        assert_eq!(tap_a(&mut executor, &sink, &mut pads, &mut at), typed("a"));
+
+        // A second press of the layer button disarms it
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            true,
+            &mut at,
+        );
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            false,
+            &mut at,
+        );
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            true,
+            &mut at,
+        );
+        press(
+            &mut executor,
+            &mut pads,
+            GamepadButton::LeftShoulder,
+            false,
+            &mut at,
+        );
+        assert_eq!(tap_a(&mut executor, &sink, &mut pads, &mut at), typed("a"));
+    }
+
+    #[test]
+    fn press_and_release_within_one_tick_fires_once() {
+        let (mut executor, sink) = executor(vec![key_mapping("a", GamepadButton::A, "a")]);
+        let pads = gamepads(&[1]);
+        let start = Instant::now();
+
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+
+        let events = vec![
+            InputEvent::new(
+                1,
+                InputEventKind::ButtonDown {
+                    button: GamepadButton::A,
+                },
+                start + Duration::from_millis(1),
+            ),
+            InputEvent::new(
+                1,
+                InputEventKind::ButtonUp {
+                    button: GamepadButton::A,
+                },
+                start + Duration::from_micros(1500),
+            ),
+        ];
+        executor.process_gamepads(events.into_iter(), &pads, &None, start + TICK_INTERVAL);
+
+        assert_eq!(
+            sink.events(),
+            vec![
+                SinkEvent::KeyDown("a".to_string()),
+                SinkEvent::KeyUp("a".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn release_on_one_pad_keeps_the_other_pad_held() {
+        let (mut executor, sink) = executor(vec![key_mapping("a", GamepadButton::A, "a")]);
+        let mut pads = gamepads(&[1, 2]);
+        let start = Instant::now();
+
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+
+        pads.get_mut(&1).unwrap().set_button(GamepadButton::A, true);
+        let events = vec![
+            button_event(1, GamepadButton::A, true, start + Duration::from_millis(1)),
+            button_event(2, GamepadButton::A, true, start + Duration::from_millis(2)),
+            button_event(2, GamepadButton::A, false, start + Duration::from_millis(3)),
+        ];
+        executor.process_gamepads(events.into_iter(), &pads, &None, start + TICK_INTERVAL);
+
+        assert!(executor.is_action_active(&key(1, "a")));
+        assert!(!executor.is_action_active(&key(2, "a")));
+        assert_eq!(
+            sink.events(),
+            vec![
+                SinkEvent::KeyDown("a".to_string()),
+                SinkEvent::KeyDown("a".to_string()),
+                SinkEvent::KeyUp("a".to_string())
+            ]
+        );
+
+        pads.get_mut(&1)
+            .unwrap()
+            .set_button(GamepadButton::A, false);
+        let events = vec![button_event(
+            1,
+            GamepadButton::A,
+            false,
+            start + Duration::from_millis(9),
+        )];
+        executor.process_gamepads(events.into_iter(), &pads, &None, start + TICK_INTERVAL * 2);
+
+        assert!(!executor.is_action_active(&key(1, "a")));
+        assert_eq!(sink.events().len(), 4);
+    }
+
+    #[test]
+    fn continuous_actions_are_tracked_per_pad() {
+        let action = Action::MouseMoveDirection {
+            direction: Direction::Right,
+            speed: 1000,
+        };
+        let (mut executor, sink) = executor(vec![Mapping::ButtonPressed(button_mapping(
+            "move",
+            GamepadButton::DPadRight,
+            action,
+        ))]);
+        let mut pads = gamepads(&[1, 2]);
+        let start = Instant::now();
+
+        for pad in pads.values_mut() {
+            pad.set_button(GamepadButton::DPadRight, true);
+        }
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+
+        assert!(
+            executor
+                .mapping_state
+                .continuous_actions
+                .contains(&key(1, "move"))
+        );
+        assert!(
+            executor
+                .mapping_state
+                .continuous_actions
+                .contains(&key(2, "move"))
+        );
+        // Each pad starts its own gesture, one tick worth of movement each
+        assert_eq!(
+            sink.events(),
+            vec![
+                SinkEvent::MoveMouse(8, 0, Coordinate::Rel),
+                SinkEvent::MoveMouse(8, 0, Coordinate::Rel)
+            ]
+        );
+
+        pads.get_mut(&2)
+            .unwrap()
+            .set_button(GamepadButton::DPadRight, false);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start + TICK_INTERVAL);
+
+        assert!(
+            executor
+                .mapping_state
+                .continuous_actions
+                .contains(&key(1, "move"))
+        );
+        assert!(
+            !executor
+                .mapping_state
+                .continuous_actions
+                .contains(&key(2, "move"))
+        );
+        assert!(
+            executor
+                .mapping_state
+                .last_moves
+                .contains_key(&key(1, "move"))
+        );
+        assert!(
+            !executor
+                .mapping_state
+                .last_moves
+                .contains_key(&key(2, "move"))
+        );
+    }
+
+    fn gyro_settings(horizontal_axis: GyroAxis, ratchet: Option<GyroRatchet>) -> GyroMouseSettings {
+        GyroMouseSettings {
+            sensitivity_x: 10,
+            sensitivity_y: 10,
+            horizontal_axis,
+            deadzone: 2,
+            ratchet,
+        }
+    }
+
+    /// Sample 10ms after the previous one, rates in degrees per second.
+    fn gyro_sample(pitch: f32, yaw: f32, roll: f32) -> SensorReading {
+        SensorReading {
+            x: pitch.to_radians(),
+            y: yaw.to_radians(),
+            z: roll.to_radians(),
+            timestamp: 1_010_000_000,
+        }
+    }
+
+    const PREVIOUS_GYRO_SAMPLE: u64 = 1_000_000_000;
+
+    fn assert_delta((x, y): (f32, f32), (expected_x, expected_y): (f32, f32)) {
+        assert!(
+            (x - expected_x).abs() < 1e-3 && (y - expected_y).abs() < 1e-3,
+            "got ({}, {}), expected ({}, {})",
+            x,
+            y,
+            expected_x,
+            expected_y
+        );
+    }
+
+    #[test]
+    fn gyro_ignores_rotations_under_the_deadzone() {
+        let settings = gyro_settings(GyroAxis::Yaw, None);
+
+        assert_delta(
+            gyro_mouse_delta(&gyro_sample(1.0, 1.0, 0.0), PREVIOUS_GYRO_SAMPLE, &settings),
+            (0.0, 0.0),
+        );
+        // 10°/s for 10ms at 10px per degree
+        assert_delta(
+            gyro_mouse_delta(
+                &gyro_sample(0.0, 10.0, 0.0),
+                PREVIOUS_GYRO_SAMPLE,
+                &settings,
+            ),
+            (-1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn gyro_horizontal_axis_follows_the_setting() {
+        let sample = gyro_sample(20.0, 10.0, 30.0);
+
+        assert_delta(
+            gyro_mouse_delta(
+                &sample,
+                PREVIOUS_GYRO_SAMPLE,
+                &gyro_settings(GyroAxis::Yaw, None),
+            ),
+            (-1.0, -2.0),
+        );
+        assert_delta(
+            gyro_mouse_delta(
+                &sample,
+                PREVIOUS_GYRO_SAMPLE,
+                &gyro_settings(GyroAxis::Roll, None),
+            ),
+            (-3.0, -2.0),
+        );
+    }
+
+    #[test]
+    fn gyro_skips_gaps_between_samples() {
+        let settings = gyro_settings(GyroAxis::Yaw, None);
+        let sample = gyro_sample(0.0, 100.0, 0.0);
+
+        // Same sample seen twice
+        assert_delta(
+            gyro_mouse_delta(&sample, sample.timestamp, &settings),
+            (0.0, 0.0),
+        );
+        // Sensor stalled for a second
+        assert_delta(
+            gyro_mouse_delta(&sample, sample.timestamp - 1_000_000_000, &settings),
+            (0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn gyro_ratchet_hold_and_toggle() {
+        let (mut executor, _) = executor(vec![]);
+        let mut pad = GamepadState::new(1, "Pad 1".to_string());
+        let hold = Some(GyroRatchet {
+            button: GamepadButton::RightShoulder,
+            mode: RatchetMode::Hold,
+        });
+        let toggle = Some(GyroRatchet {
+            button: GamepadButton::RightShoulder,
+            mode: RatchetMode::Toggle,
+        });
+        let hold_key = key(1, "hold");
+        let toggle_key = key(1, "toggle");
+
+        let step = |executor: &mut MappingExecutor, pad: &GamepadState| {
+            (
+                executor.update_gyro_ratchet(pad, &hold_key, &hold),
+                executor.update_gyro_ratchet(pad, &toggle_key, &toggle),
+            )
+        };
+
+        assert_eq!(step(&mut executor, &pad), (false, false));
+
+        pad.set_button(GamepadButton::RightShoulder, true);
+        assert_eq!(step(&mut executor, &pad), (true, true));
+        assert_eq!(step(&mut executor, &pad), (true, true));
+
+        pad.set_button(GamepadButton::RightShoulder, false);
+        assert_eq!(step(&mut executor, &pad), (false, true));
+
+        pad.set_button(GamepadButton::RightShoulder, true);
+        assert_eq!(step(&mut executor, &pad), (true, false));
+
+        pad.set_button(GamepadButton::RightShoulder, false);
+        assert_eq!(step(&mut executor, &pad), (false, false));
+
+        assert!(executor.update_gyro_ratchet(&pad, &key(1, "free"), &None));
+    }
+
+    #[test]
+    fn replayed_axis_values_are_not_calibrated_twice() {
+        let (mut executor, _) = executor(vec![]);
+        let mut pad = GamepadState::new(1, "Pad 1".to_string());
+        pad.set_calibration(Some(ControllerCalibration {
+            axes: HashMap::from([(
+                GamepadAxis::LeftX,
+                AxisCalibration {
+                    center: 1000,
+                    min: -30000,
+                    max: 30000,
+                },
+            )]),
+            left_stick: None,
+            right_stick: None,
+        }));
+        pad.set_axis(GamepadAxis::LeftX, 15500);
+        let calibrated = pad.axis_value(&GamepadAxis::LeftX);
+        let pads = HashMap::from([(1, pad)]);
+
+        let event = InputEvent::new(
+            1,
+            InputEventKind::AxisChanged {
+                axis: GamepadAxis::LeftX,
+                value: calibrated,
+            },
+            Instant::now(),
+        );
+        executor.replay_button_edges(std::iter::once(event), &pads, &None);
+
+        assert_eq!(
+            executor.mapping_state.previous_gamepad_states[&1].axis_value(&GamepadAxis::LeftX),
+            calibrated
+        );
+    }
+
+    #[test]
+    fn turbo_phases_shorter_than_a_tick_are_not_dropped() {
+        let mapping = Mapping::ButtonPressed(ButtonMapping {
+            turbo: Some(TurboSettings {
+                rate: MAX_TURBO_RATE,
+                duty_cycle: 1,
+                toggle: false,
+            }),
+            ..button_mapping("a", GamepadButton::A, press_key("a"))
+        });
+        let (mut executor, sink) = executor(vec![mapping]);
+        let mut pads = gamepads(&[1]);
+        pads.get_mut(&1).unwrap().set_button(GamepadButton::A, true);
+        let start = Instant::now();
+
+        // 200ms of ticks, the 0.3ms presses are stretched to one tick
+        for tick in 0..=25 {
+            executor.process_gamepads(
+                std::iter::empty(),
+                &pads,
+                &None,
+                start + TICK_INTERVAL * tick,
+            );
+        }
+
+        let events = sink.events();
+        let presses = events
+            .iter()
+            .filter(|event| **event == SinkEvent::KeyDown("a".to_string()))
+            .count();
+
+        assert_eq!(presses, 5);
+        assert!(events.chunks(2).all(|pair| {
+            pair[0] == SinkEvent::KeyDown("a".to_string())
+                && pair
+                    .get(1)
+                    .is_none_or(|up| *up == SinkEvent::KeyUp("a".to_string()))
+        }));
+    }
+
+    #[test]
+    fn skipped_trigger_mappings_leave_no_deadline() {
+        let mapping = Mapping::ButtonPressed(ButtonMapping {
+            trigger: Some(ButtonTrigger::Hold { ms: 500 }),
+            ..button_mapping("a", GamepadButton::A, press_key("a"))
+        });
+        let mut pads = gamepads(&[1]);
+        pads.get_mut(&1).unwrap().set_button(GamepadButton::A, true);
+        let start = Instant::now();
+
+        // Paused while the hold is pending
+        let (mut paused_executor, _) = executor(vec![mapping.clone()]);
+        paused_executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+        assert_eq!(
+            paused_executor.next_trigger_deadline(),
+            Some(start + Duration::from_millis(500))
+        );
+
+        let paused = Some(vec![Action::ToogleMappingActive]);
+        paused_executor.process_gamepads(std::iter::empty(), &pads, &paused, start + TICK_INTERVAL);
+        assert_eq!(paused_executor.next_trigger_deadline(), None);
+
+        // Mapping removed once the hold started, the action is released
+        let (mut executor, sink) = executor(vec![mapping]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+        executor.process_gamepads(
+            std::iter::empty(),
+            &pads,
+            &None,
+            start + Duration::from_millis(500),
+        );
+        assert_eq!(sink.events(), vec![SinkEvent::KeyDown("a".to_string())]);
+
+        executor.config.mappings.clear();
+        executor.process_gamepads(
+            std::iter::empty(),
+            &pads,
+            &None,
+            start + Duration::from_millis(600),
+        );
+        assert_eq!(executor.next_trigger_deadline(), None);
+        assert_eq!(
+            sink.events(),
+            vec![
+                SinkEvent::KeyDown("a".to_string()),
+                SinkEvent::KeyUp("a".to_string())
+            ]
+        );
+    }
+
+    fn touchpad_mapping(tap_to_click: bool, right_click_zone: Option<u8>) -> Mapping {
+        Mapping::Touchpad(TouchpadMapping {
+            id: "touchpad".to_string(),
+            sensitivity: 10,
+            scroll_speed: 10,
+            tap_to_click,
+            right_click_zone,
+            conditions: vec![],
+            target: None,
+        })
+    }
+
+    /// Replaces the fingers on the pad, each given as its `(x, y)` position.
+    fn touch(pads: &mut HashMap<u32, GamepadState>, fingers: &[(f32, f32)]) {
+        let pad = pads.get_mut(&1).unwrap();
+        let released: Vec<TouchpadFinger> = pad.touchpad_fingers().to_vec();
+
+        for finger in released {
+            pad.set_touchpad_finger(finger, false);
+        }
+
+        for (index, (x, y)) in fingers.iter().enumerate() {
+            let finger = TouchpadFinger {
+                finger: index as u8,
+                x: *x,
+                y: *y,
+                pressure: 1.0,
+            };
+            pad.set_touchpad_finger(finger, true);
+        }
+    }
+
+    #[test]
+    fn short_touchpad_taps_click() {
+        let (mut executor, sink) = executor(vec![touchpad_mapping(true, None)]);
+        let mut pads = gamepads(&[1]);
+        let start = Instant::now();
+
+        touch(&mut pads, &[(0.5, 0.5)]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+        touch(&mut pads, &[]);
+        executor.process_gamepads(
+            std::iter::empty(),
+            &pads,
+            &None,
+            start + Duration::from_millis(100),
+        );
+        assert_eq!(
+            sink.events(),
+            vec![SinkEvent::MouseButton(
+                MouseButton::Left,
+                enigo::Direction::Click
+            )]
+        );
+
+        // Held too long to count as a tap
+        let later = start + Duration::from_secs(1);
+        touch(&mut pads, &[(0.5, 0.5)]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, later);
+        touch(&mut pads, &[]);
+        executor.process_gamepads(
+            std::iter::empty(),
+            &pads,
+            &None,
+            later + Duration::from_millis(300),
+        );
+        assert_eq!(sink.events().len(), 1);
+
+        // Two finger taps are right clicks
+        let later = start + Duration::from_secs(2);
+        touch(&mut pads, &[(0.4, 0.5), (0.6, 0.5)]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, later);
+        touch(&mut pads, &[]);
+        executor.process_gamepads(
+            std::iter::empty(),
+            &pads,
+            &None,
+            later + Duration::from_millis(100),
+        );
+        assert_eq!(
+            sink.events()[1..],
+            [SinkEvent::MouseButton(
+                MouseButton::Right,
+                enigo::Direction::Click
+            )]
+        );
+    }
+
+    #[test]
+    fn two_fingers_scroll_the_content_along() {
+        let (mut executor, sink) = executor(vec![touchpad_mapping(false, None)]);
+        let mut pads = gamepads(&[1]);
+        let start = Instant::now();
+
+        touch(&mut pads, &[(0.25, 0.25), (0.5, 0.25)]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+        touch(&mut pads, &[(0.25, 0.75), (0.5, 0.75)]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start + TICK_INTERVAL);
+
+        assert_eq!(sink.events(), vec![SinkEvent::Scroll(-5, Axis::Vertical)]);
+    }
+
+    #[test]
+    fn right_click_zone_turns_clicks_into_right_clicks() {
+        let (mut executor, sink) = executor(vec![touchpad_mapping(true, Some(70))]);
+        let mut pads = gamepads(&[1]);
+        let start = Instant::now();
+
+        touch(&mut pads, &[(0.8, 0.5)]);
+        pads.get_mut(&1)
+            .unwrap()
+            .set_button(GamepadButton::Touchpad, true);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+        pads.get_mut(&1)
+            .unwrap()
+            .set_button(GamepadButton::Touchpad, false);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start + TICK_INTERVAL);
+        assert_eq!(
+            sink.events(),
+            vec![
+                SinkEvent::MouseButton(MouseButton::Right, enigo::Direction::Press),
+                SinkEvent::MouseButton(MouseButton::Right, enigo::Direction::Release)
+            ]
+        );
+
+        // A tap left of the zone stays a left click
+        let later = start + Duration::from_secs(1);
+        touch(&mut pads, &[]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, later);
+        touch(&mut pads, &[(0.3, 0.5)]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, later + TICK_INTERVAL);
+        touch(&mut pads, &[]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, later + TICK_INTERVAL * 2);
+        assert_eq!(
+            sink.events()[2..],
+            [SinkEvent::MouseButton(
+                MouseButton::Left,
+                enigo::Direction::Click
+            )]
+        );
+    }
+
+    #[test]
+    fn skipped_touchpad_mappings_release_their_click() {
+        let mapping = touchpad_mapping(false, None);
+        let mut pads = gamepads(&[1]);
+        pads.get_mut(&1)
+            .unwrap()
+            .set_button(GamepadButton::Touchpad, true);
+        let start = Instant::now();
+
+        let (mut executor, sink) = executor(vec![mapping]);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+        assert_eq!(
+            sink.events(),
+            vec![SinkEvent::MouseButton(
+                MouseButton::Left,
+                enigo::Direction::Press
+            )]
+        );
+
+        // Paused while the pad is still clicked
+        let paused = Some(vec![Action::ToogleMappingActive]);
+        executor.process_gamepads(std::iter::empty(), &pads, &paused, start + TICK_INTERVAL);
+        assert_eq!(
+            sink.events(),
+            vec![
+                SinkEvent::MouseButton(MouseButton::Left, enigo::Direction::Press),
+                SinkEvent::MouseButton(MouseButton::Left, enigo::Direction::Release)
+            ]
+        );
+        assert!(executor.mapping_state.touchpads.is_empty());
+    }
+
+    #[test]
+    fn pausing_or_switching_profile_cancels_macros() {
+        let (mut executor, _) = executor(vec![]);
+        let pads = gamepads(&[1]);
+        let start = Instant::now();
+
+        let (running, cancelled) = RunningMacro::detached(false);
+        executor
+            .mapping_state
+            .macros
+            .insert(key(1, "macro"), running);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+        assert!(!*cancelled.borrow());
+
+        let paused = Some(vec![Action::ToogleMappingActive]);
+        executor.process_gamepads(std::iter::empty(), &pads, &paused, start + TICK_INTERVAL);
+        assert!(*cancelled.borrow());
+        assert!(executor.mapping_state.macros.is_empty());
+
+        let (running, cancelled) = RunningMacro::detached(false);
+        executor
+            .mapping_state
+            .macros
+            .insert(key(1, "macro"), running);
+        let mut config = executor.config.clone();
+        executor.set_config(config.clone());
+        assert!(!*cancelled.borrow());
+
+        config.active_profile = Some("other".to_string());
+        executor.set_config(config);
+        assert!(*cancelled.borrow());
+    }
+
+    #[test]
+    fn axis_triggers_go_through_the_deadzone_and_curve() {
+        let mapping = Mapping::AxisTrigger(AxisTriggerMapping {
+            id: "trigger".to_string(),
+            axis: GamepadAxis::TriggerLeft,
+            threshold: 0.33,
+            conditions: vec![],
+            target: None,
+            action: Action::PressKeys {
+                keys: vec!["a".to_string()],
+            },
+            feedback: None,
+            curve: Some(ResponseCurve::Power { exponent: 2.0 }),
+        });
+        let (mut executor, sink) = executor(vec![mapping]);
+        let mut pads = gamepads(&[1]);
+        let start = Instant::now();
+
+        // 0.6 raw is 0.56 past the 0.1 deadzone, 0.31 once squared, 0.36 without the deadzone
+        let partial = (0.6 * i16::MAX as f32) as i16;
+        pads.get_mut(&1)
+            .unwrap()
+            .set_axis(GamepadAxis::TriggerLeft, partial);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+        assert_eq!(sink.events(), vec![]);
+
+        pads.get_mut(&1)
+            .unwrap()
+            .set_axis(GamepadAxis::TriggerLeft, i16::MAX);
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start + TICK_INTERVAL);
+        assert_eq!(sink.events(), vec![SinkEvent::KeyDown("a".to_string())]);
+    }
+
+    #[test]
+    fn movement_follows_the_pass_clock() {
+        let action = Action::MouseMoveDirection {
+            direction: Direction::Right,
+            speed: 1000,
+        };
+        let mapping =
+            Mapping::ButtonPressed(button_mapping("right", GamepadButton::DPadRight, action));
+        let (mut executor, sink) = executor(vec![mapping]);
+        let mut pads = gamepads(&[1]);
+        pads.get_mut(&1)
+            .unwrap()
+            .set_button(GamepadButton::DPadRight, true);
+        let start = Instant::now();
+
+        // The first move counts as one tick, the next ones as the time between passes
+        executor.process_gamepads(std::iter::empty(), &pads, &None, start);
+        executor.process_gamepads(
+            std::iter::empty(),
+            &pads,
+            &None,
+            start + Duration::from_millis(20),
+        );
+
+        assert_eq!(
+            sink.events(),
+            vec![
+                SinkEvent::MoveMouse(8, 0, Coordinate::Rel),
+                SinkEvent::MoveMouse(20, 0, Coordinate::Rel)
+            ]
+        );
+    }
+}
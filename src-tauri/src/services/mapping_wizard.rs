@@ -0,0 +1,170 @@
+use std::{collections::HashMap, error::Error, time::Duration};
+
+use tokio::sync::broadcast::{self, error::RecvError, error::TryRecvError};
+
+use crate::{
+    model::sdl_mapping::{
+        MappingWizardUpdate, RawInput, RawInputEvent, SDL_ELEMENTS, SdlElementKind,
+        build_mapping_string, raw_input_binding,
+    },
+    services::{
+        config_service::ConfigService,
+        gamepad_service::{GAMEPAD_STATE, send_backend_command},
+        input_backend::BackendCommand,
+    },
+};
+
+/// Time given to press each element before it is skipped.
+const ELEMENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Turns raw capture off even when the wizard is cancelled halfway.
+struct RawCaptureGuard {
+    gamepad_id: u32,
+}
+
+impl RawCaptureGuard {
+    fn new(gamepad_id: u32) -> Self {
+        send_backend_command(BackendCommand::SetRawCapture {
+            id: gamepad_id,
+            enabled: true,
+        });
+
+        Self { gamepad_id }
+    }
+}
+
+impl Drop for RawCaptureGuard {
+    fn drop(&mut self) {
+        send_backend_command(BackendCommand::SetRawCapture {
+            id: self.gamepad_id,
+            enabled: false,
+        });
+    }
+}
+
+/// Asks for every SDL element in turn, records the raw input pressed for it and stores the resulting mapping string.
+pub async fn run_mapping_wizard<F: Fn(MappingWizardUpdate)>(
+    gamepad_id: u32,
+    on_update: F,
+) -> Result<String, Box<dyn Error>> {
+    let (gamepad, mut receiver) = {
+        let state = GAMEPAD_STATE.read().unwrap();
+        let gamepad = state.get_gamepads().remove(&gamepad_id);
+        (gamepad, state.subscribe_raw_input())
+    };
+
+    let gamepad = gamepad.ok_or(format!("Gamepad {} is not connected", gamepad_id))?;
+    let guid = gamepad.identity().guid.clone();
+
+    if guid.is_empty() {
+        return Err(format!("Gamepad {} has no GUID", gamepad_id).into());
+    }
+
+    let _capture = RawCaptureGuard::new(gamepad_id);
+    let mut bindings: Vec<(&str, String)> = Vec::new();
+    let mut axis_rests: HashMap<u8, i16> = HashMap::new();
+
+    for (step, element) in SDL_ELEMENTS.iter().enumerate() {
+        on_update(MappingWizardUpdate::Prompt {
+            element: element.name.to_string(),
+            step: step + 1,
+            total: SDL_ELEMENTS.len(),
+        });
+
+        // Releases and leftovers from the previous element
+        discard_pending(&mut receiver, gamepad_id, &mut axis_rests);
+
+        let binding = tokio::time::timeout(
+            ELEMENT_TIMEOUT,
+            next_binding(
+                &mut receiver,
+                gamepad_id,
+                element.kind,
+                &bindings,
+                &mut axis_rests,
+            ),
+        )
+        .await
+        .ok()
+        .flatten();
+
+        on_update(MappingWizardUpdate::Recorded {
+            element: element.name.to_string(),
+            binding: binding.clone(),
+        });
+
+        if let Some(binding) = binding {
+            bindings.push((element.name, binding));
+        }
+    }
+
+    if bindings.is_empty() {
+        return Err("No input was recorded".into());
+    }
+
+    let mapping = build_mapping_string(&guid, gamepad.name(), &bindings);
+    ConfigService::get_instance().add_sdl_mappings(vec![mapping.clone()]);
+
+    on_update(MappingWizardUpdate::Done {
+        mapping: mapping.clone(),
+    });
+
+    Ok(mapping)
+}
+
+async fn next_binding(
+    receiver: &mut broadcast::Receiver<RawInputEvent>,
+    gamepad_id: u32,
+    kind: SdlElementKind,
+    bindings: &[(&str, String)],
+    axis_rests: &mut HashMap<u8, i16>,
+) -> Option<String> {
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return None,
+        };
+
+        if event.gamepad_id != gamepad_id {
+            continue;
+        }
+
+        let axis_rest = remember_axis_rest(axis_rests, &event.input);
+        let Some(binding) = raw_input_binding(&event.input, kind, axis_rest) else {
+            continue;
+        };
+
+        // An input already bound is most likely the previous element still held
+        if bindings.iter().any(|(_, existing)| *existing == binding) {
+            continue;
+        }
+
+        return Some(binding);
+    }
+}
+
+/// Drops the pending events, an axis seen for the first time still gets its rest value.
+fn discard_pending(
+    receiver: &mut broadcast::Receiver<RawInputEvent>,
+    gamepad_id: u32,
+    axis_rests: &mut HashMap<u8, i16>,
+) {
+    loop {
+        match receiver.try_recv() {
+            Ok(event) if event.gamepad_id == gamepad_id => {
+                remember_axis_rest(axis_rests, &event.input);
+            }
+            Ok(_) | Err(TryRecvError::Lagged(_)) => continue,
+            Err(_) => break,
+        }
+    }
+}
+
+/// The first value seen of an axis is taken as its rest position, returns that rest position.
+fn remember_axis_rest(axis_rests: &mut HashMap<u8, i16>, input: &RawInput) -> i16 {
+    match *input {
+        RawInput::Axis { index, value } => *axis_rests.entry(index).or_insert(value),
+        _ => 0,
+    }
+}
@@ -1,5 +1,13 @@
+pub mod calibration_wizard;
 pub mod config_service;
 pub mod gamepad_service;
+pub mod input_backend;
+pub mod input_sink;
 pub mod ipc_service;
+pub mod led_service;
+pub mod macro_runner;
 pub mod mapping_service;
+pub mod mapping_wizard;
+pub mod scripted_backend;
+pub mod sdl_backend;
 pub mod virtual_keyboard;
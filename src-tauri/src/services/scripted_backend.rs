@@ -0,0 +1,225 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fs,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::gamepad::{
+        ControllerIdentity, GamepadAxis, GamepadButton, GamepadState, HatPosition, MotionSensor,
+        PowerState, SensorReading, TouchpadFinger,
+    },
+    model::sdl_mapping::RawInput,
+    services::input_backend::{BackendCommand, BackendEvent, InputBackend},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ScriptedEvent {
+    Connect {
+        id: u32,
+        name: String,
+        #[serde(default)]
+        has_gyro: bool,
+        #[serde(default)]
+        has_accel: bool,
+        #[serde(default)]
+        identity: Option<ControllerIdentity>,
+    },
+    ConnectJoystick {
+        id: u32,
+        name: String,
+        buttons: u8,
+        axes: u8,
+        #[serde(default)]
+        hats: u8,
+    },
+    Disconnect {
+        id: u32,
+    },
+    Button {
+        id: u32,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    Axis {
+        id: u32,
+        axis: GamepadAxis,
+        value: i16,
+    },
+    Hat {
+        id: u32,
+        hat: u8,
+        position: HatPosition,
+    },
+    Sensor {
+        id: u32,
+        sensor: MotionSensor,
+        reading: SensorReading,
+    },
+    Touchpad {
+        id: u32,
+        finger: TouchpadFinger,
+        pressed: bool,
+    },
+    Power {
+        id: u32,
+        power: PowerState,
+    },
+    RawInput {
+        id: u32,
+        input: RawInput,
+    },
+    Quit,
+}
+
+impl From<ScriptedEvent> for BackendEvent {
+    fn from(event: ScriptedEvent) -> Self {
+        match event {
+            ScriptedEvent::Connect {
+                id,
+                name,
+                has_gyro,
+                has_accel,
+                identity,
+            } => {
+                let mut gamepad = GamepadState::new(id, name);
+                gamepad.set_motion_support(has_gyro, has_accel);
+
+                if let Some(identity) = identity {
+                    gamepad.set_identity(identity);
+                }
+
+                BackendEvent::DeviceAdded(Box::new(gamepad))
+            }
+            ScriptedEvent::ConnectJoystick {
+                id,
+                name,
+                buttons,
+                axes,
+                hats,
+            } => BackendEvent::DeviceAdded(Box::new(GamepadState::new_joystick(
+                id, name, buttons, axes, hats,
+            ))),
+            ScriptedEvent::Disconnect { id } => BackendEvent::DeviceRemoved { id },
+            ScriptedEvent::Button {
+                id,
+                button,
+                pressed,
+            } => BackendEvent::Button {
+                id,
+                button,
+                pressed,
+            },
+            ScriptedEvent::Axis { id, axis, value } => BackendEvent::Axis { id, axis, value },
+            ScriptedEvent::Hat { id, hat, position } => BackendEvent::Hat { id, hat, position },
+            ScriptedEvent::Sensor {
+                id,
+                sensor,
+                reading,
+            } => BackendEvent::Sensor {
+                id,
+                sensor,
+                reading,
+            },
+            ScriptedEvent::Touchpad {
+                id,
+                finger,
+                pressed,
+            } => BackendEvent::Touchpad {
+                id,
+                finger,
+                pressed,
+            },
+            ScriptedEvent::Power { id, power } => BackendEvent::Power { id, power },
+            ScriptedEvent::RawInput { id, input } => BackendEvent::RawInput { id, input },
+            ScriptedEvent::Quit => BackendEvent::Quit,
+        }
+    }
+}
+
+/// One step of a script, `delay_ms` is relative to the previous step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptStep {
+    #[serde(default)]
+    pub delay_ms: u64,
+    pub event: ScriptedEvent,
+}
+
+/// Replays a timed list of events, used to drive the mapping pipeline without a physical controller.
+/// Commands sent to the controllers are recorded instead of being played.
+pub struct ScriptedBackend {
+    steps: VecDeque<ScriptStep>,
+    last_step_at: Option<Instant>,
+    sent_commands: Arc<Mutex<Vec<BackendCommand>>>,
+}
+
+impl ScriptedBackend {
+    pub fn new(steps: Vec<ScriptStep>) -> Self {
+        Self {
+            steps: steps.into(),
+            last_step_at: None,
+            sent_commands: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Shared handle on the recorded commands, stays valid once the backend is handed to the monitor.
+    #[cfg(test)]
+    pub fn sent_commands(&self) -> Arc<Mutex<Vec<BackendCommand>>> {
+        self.sent_commands.clone()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let steps: Vec<ScriptStep> = serde_json::from_str(json)?;
+        Ok(Self::new(steps))
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read input script {}: {}", path, e))?;
+        Self::from_json(&json)
+    }
+}
+
+impl InputBackend for ScriptedBackend {
+    fn name(&self) -> &'static str {
+        "scripted"
+    }
+
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<BackendEvent>, Box<dyn Error>> {
+        let delay = match self.steps.front() {
+            Some(step) => Duration::from_millis(step.delay_ms),
+            None => return Ok(None),
+        };
+
+        let due = *self.last_step_at.get_or_insert_with(Instant::now) + delay;
+        let now = Instant::now();
+
+        if due > now {
+            thread::sleep((due - now).min(timeout));
+
+            if Instant::now() < due {
+                return Ok(None);
+            }
+        }
+
+        self.last_step_at = Some(due);
+
+        Ok(self.steps.pop_front().map(|step| step.event.into()))
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    fn send_command(&mut self, command: &BackendCommand) -> Result<(), Box<dyn Error>> {
+        self.sent_commands.lock().unwrap().push(command.clone());
+        Ok(())
+    }
+}
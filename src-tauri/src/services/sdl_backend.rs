@@ -0,0 +1,570 @@
+use sdl3::{
+    EventPump, GamepadSubsystem, JoystickSubsystem, Sdl,
+    event::Event,
+    gamepad::{Axis, Button, Gamepad},
+    joystick::{ConnectionState, HatState, Joystick, PowerInfo, PowerLevel},
+    sensor::SensorType,
+    sys::events::SDL_EVENT_JOYSTICK_BATTERY_UPDATED,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    model::gamepad::{
+        BatteryStatus, ConnectionType, GamepadAxis, GamepadButton, GamepadState, HatPosition,
+        MotionSensor, PowerState, SensorReading, TouchpadFinger,
+    },
+    model::sdl_mapping::RawInput,
+    services::input_backend::{BackendCommand, BackendEvent, InputBackend},
+};
+
+/// Fallback poll of the power states, for the devices that don't send battery updates.
+const POWER_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct SdlBackend {
+    _sdl_context: Sdl,
+    controller_subsystem: GamepadSubsystem,
+    joystick_subsystem: JoystickSubsystem,
+    event_pump: EventPump,
+    gamepads: HashMap<u32, Gamepad>,
+    /// Devices without a gamepad mapping, SDL also emits joystick events for gamepads so those are ignored.
+    joysticks: HashMap<u32, Joystick>,
+    /// Devices whose joystick events are reported as raw input (mapping wizard)
+    raw_capture: HashSet<u32>,
+    pending_events: VecDeque<BackendEvent>,
+    power_states: HashMap<u32, PowerState>,
+    last_power_refresh: Instant,
+}
+
+impl SdlBackend {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let sdl_context: Sdl = sdl3::init()?;
+        let controller_subsystem = sdl_context.gamepad()?;
+        let joystick_subsystem = sdl_context.joystick()?;
+
+        controller_subsystem.set_events_processing_state(true);
+        joystick_subsystem.set_events_processing_state(true);
+
+        let event_pump = sdl_context
+            .event_pump()
+            .map_err(|e| format!("Failed to create event pump: {}", e))?;
+
+        let mut backend = Self {
+            _sdl_context: sdl_context,
+            controller_subsystem,
+            joystick_subsystem,
+            event_pump,
+            gamepads: HashMap::new(),
+            joysticks: HashMap::new(),
+            raw_capture: HashSet::new(),
+            pending_events: VecDeque::new(),
+            power_states: HashMap::new(),
+            last_power_refresh: Instant::now(),
+        };
+
+        backend.open_connected_devices()?;
+
+        Ok(backend)
+    }
+
+    /// Queues a power event for every gamepad whose battery or connection changed since the last refresh.
+    fn refresh_power_states(&mut self) {
+        self.last_power_refresh = Instant::now();
+
+        let gamepad_powers = self
+            .gamepads
+            .iter()
+            .map(|(id, gamepad)| (*id, read_power_state(gamepad)));
+        let joystick_powers = self
+            .joysticks
+            .iter()
+            .map(|(id, joystick)| (*id, read_joystick_power_state(joystick)));
+        let powers: Vec<(u32, PowerState)> = gamepad_powers.chain(joystick_powers).collect();
+
+        for (id, power) in powers {
+            if self.power_states.get(&id) == Some(&power) {
+                continue;
+            }
+
+            self.power_states.insert(id, power);
+            self.pending_events
+                .push_back(BackendEvent::Power { id, power });
+        }
+    }
+
+    fn convert_event(&mut self, event: Event) -> Option<BackendEvent> {
+        match event {
+            Event::Quit { .. } => Some(BackendEvent::Quit),
+            Event::ControllerButtonDown { which, button, .. }
+            | Event::ControllerButtonUp { which, button, .. } => {
+                let pressed = matches!(event, Event::ControllerButtonDown { .. });
+
+                Some(BackendEvent::Button {
+                    id: which,
+                    button: sdl_button_to_gamepad_button(&button),
+                    pressed,
+                })
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => Some(BackendEvent::Axis {
+                id: which,
+                axis: sdl_axis_to_gamepad_axis(&axis),
+                value,
+            }),
+            Event::ControllerSensorUpdated {
+                timestamp,
+                which,
+                sensor,
+                data,
+                ..
+            } => sdl_sensor_to_motion_sensor(&sensor).map(|sensor| BackendEvent::Sensor {
+                id: which,
+                sensor,
+                reading: SensorReading {
+                    x: data[0],
+                    y: data[1],
+                    z: data[2],
+                    timestamp,
+                },
+            }),
+            Event::ControllerTouchpadDown {
+                which,
+                touchpad,
+                finger,
+                x,
+                y,
+                pressure,
+                ..
+            }
+            | Event::ControllerTouchpadMotion {
+                which,
+                touchpad,
+                finger,
+                x,
+                y,
+                pressure,
+                ..
+            }
+            | Event::ControllerTouchpadUp {
+                which,
+                touchpad,
+                finger,
+                x,
+                y,
+                pressure,
+                ..
+            } => {
+                // Only the main touchpad is tracked
+                if touchpad != 0 || finger < 0 {
+                    return None;
+                }
+
+                let pressed = !matches!(event, Event::ControllerTouchpadUp { .. });
+
+                Some(BackendEvent::Touchpad {
+                    id: which,
+                    finger: TouchpadFinger {
+                        finger: finger as u8,
+                        x,
+                        y,
+                        pressure,
+                    },
+                    pressed,
+                })
+            }
+            Event::ControllerDeviceAdded { which, .. } => {
+                if self.gamepads.contains_key(&which) {
+                    return None;
+                }
+
+                // A joystick becomes a gamepad once a custom mapping is added for it
+                self.joysticks.remove(&which);
+
+                self.add_device(which)
+                    .map_err(|e| eprintln!("Unable to open gamepad {}: {}", which, e))
+                    .ok()
+                    .map(Box::new)
+                    .map(BackendEvent::DeviceAdded)
+            }
+            Event::ControllerDeviceRemoved { which, .. } => self
+                .remove_device(which)
+                .then_some(BackendEvent::DeviceRemoved { id: which }),
+            Event::JoyDeviceAdded { which, .. } => {
+                if self.joysticks.contains_key(&which)
+                    || self.controller_subsystem.is_gamepad(which)
+                {
+                    return None;
+                }
+
+                self.add_joystick(which)
+                    .map_err(|e| eprintln!("Unable to open joystick {}: {}", which, e))
+                    .ok()
+                    .map(Box::new)
+                    .map(BackendEvent::DeviceAdded)
+            }
+            Event::JoyDeviceRemoved { which, .. } => {
+                // Gamepads are removed through their own event
+                if !self.joysticks.contains_key(&which) {
+                    return None;
+                }
+
+                self.remove_device(which)
+                    .then_some(BackendEvent::DeviceRemoved { id: which })
+            }
+            Event::JoyButtonDown {
+                which, button_idx, ..
+            }
+            | Event::JoyButtonUp {
+                which, button_idx, ..
+            } if self.raw_capture.contains(&which) => Some(BackendEvent::RawInput {
+                id: which,
+                input: RawInput::Button {
+                    index: button_idx,
+                    pressed: matches!(event, Event::JoyButtonDown { .. }),
+                },
+            }),
+            Event::JoyAxisMotion {
+                which,
+                axis_idx,
+                value,
+                ..
+            } if self.raw_capture.contains(&which) => Some(BackendEvent::RawInput {
+                id: which,
+                input: RawInput::Axis {
+                    index: axis_idx,
+                    value,
+                },
+            }),
+            Event::JoyHatMotion {
+                which,
+                hat_idx,
+                state,
+                ..
+            } if self.raw_capture.contains(&which) => Some(BackendEvent::RawInput {
+                id: which,
+                input: RawInput::Hat {
+                    index: hat_idx,
+                    position: sdl_hat_to_hat_position(&state),
+                },
+            }),
+            Event::JoyButtonDown {
+                which, button_idx, ..
+            }
+            | Event::JoyButtonUp {
+                which, button_idx, ..
+            } if self.joysticks.contains_key(&which) => {
+                let pressed = matches!(event, Event::JoyButtonDown { .. });
+
+                Some(BackendEvent::Button {
+                    id: which,
+                    button: GamepadButton::JoystickButton(button_idx),
+                    pressed,
+                })
+            }
+            Event::JoyAxisMotion {
+                which,
+                axis_idx,
+                value,
+                ..
+            } if self.joysticks.contains_key(&which) => Some(BackendEvent::Axis {
+                id: which,
+                axis: GamepadAxis::JoystickAxis(axis_idx),
+                value,
+            }),
+            Event::JoyHatMotion {
+                which,
+                hat_idx,
+                state,
+                ..
+            } if self.joysticks.contains_key(&which) => Some(BackendEvent::Hat {
+                id: which,
+                hat: hat_idx,
+                position: sdl_hat_to_hat_position(&state),
+            }),
+            // SDL sends the device with the update, but sdl3-rs turns the event into `Unknown` which only keeps
+            // its type, so every open device is read again. The changes are queued and `next_event` hands out
+            // the rest before waiting on SDL again.
+            Event::Unknown { type_, .. } if type_ == SDL_EVENT_JOYSTICK_BATTERY_UPDATED.0 => {
+                self.refresh_power_states();
+                self.pending_events.pop_front()
+            }
+            _ => None,
+        }
+    }
+
+    /// Opens the devices connected before startup, SDL may also report them as added afterwards.
+    fn open_connected_devices(&mut self) -> Result<(), Box<dyn Error>> {
+        for id in self.controller_subsystem.gamepads()? {
+            match self.add_device(id) {
+                Ok(gamepad) => self
+                    .pending_events
+                    .push_back(BackendEvent::DeviceAdded(Box::new(gamepad))),
+                Err(e) => eprintln!("Unable to open gamepad {}: {}", id, e),
+            }
+        }
+
+        for id in self.joystick_subsystem.joysticks()? {
+            if self.controller_subsystem.is_gamepad(id) {
+                continue;
+            }
+
+            match self.add_joystick(id) {
+                Ok(joystick) => self
+                    .pending_events
+                    .push_back(BackendEvent::DeviceAdded(Box::new(joystick))),
+                Err(e) => eprintln!("Unable to open joystick {}: {}", id, e),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_device(&mut self, id: u32) -> Result<GamepadState, Box<dyn Error>> {
+        let gamepad = self.controller_subsystem.open(id)?;
+
+        if !gamepad.connected() {
+            return Err(format!("Gamepad {} is not connected", id).into());
+        }
+
+        let mut state = GamepadState::from_sdl_gamepad(&gamepad);
+        let power = read_power_state(&gamepad);
+
+        state.set_power(power);
+        self.power_states.insert(id, power);
+
+        enable_motion_sensors(&gamepad, &state);
+
+        self.gamepads.insert(id, gamepad);
+
+        Ok(state)
+    }
+
+    /// Drops the SDL handle, which closes the device.
+    fn remove_device(&mut self, id: u32) -> bool {
+        self.raw_capture.remove(&id);
+        self.power_states.remove(&id);
+
+        let gamepad = self.gamepads.remove(&id);
+        let joystick = self.joysticks.remove(&id);
+
+        gamepad.is_some() || joystick.is_some()
+    }
+
+    fn add_joystick(&mut self, id: u32) -> Result<GamepadState, Box<dyn Error>> {
+        let joystick = self.joystick_subsystem.open(id)?;
+        let mut state = GamepadState::from_sdl_joystick(&joystick);
+        let power = read_joystick_power_state(&joystick);
+
+        state.set_power(power);
+        self.power_states.insert(id, power);
+        self.joysticks.insert(id, joystick);
+
+        Ok(state)
+    }
+}
+
+impl InputBackend for SdlBackend {
+    fn name(&self) -> &'static str {
+        "sdl"
+    }
+
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<BackendEvent>, Box<dyn Error>> {
+        if self.last_power_refresh.elapsed() >= POWER_REFRESH_INTERVAL {
+            self.refresh_power_states();
+        }
+
+        if let Some(event) = self.pending_events.pop_front() {
+            return Ok(Some(event));
+        }
+
+        let until_refresh =
+            POWER_REFRESH_INTERVAL.saturating_sub(self.last_power_refresh.elapsed());
+
+        let event = self
+            .event_pump
+            .wait_event_timeout(timeout.min(until_refresh).as_millis() as u32);
+
+        Ok(event.and_then(|event| self.convert_event(event)))
+    }
+
+    fn send_command(&mut self, command: &BackendCommand) -> Result<(), Box<dyn Error>> {
+        match command {
+            BackendCommand::Rumble { id, rumble } => {
+                let gamepad = self
+                    .gamepads
+                    .get_mut(id)
+                    .ok_or(format!("Gamepad {} is not open", id))?;
+
+                let duration_ms = rumble.duration_ms as u32;
+
+                gamepad.set_rumble(
+                    percent_to_strength(rumble.low_frequency),
+                    percent_to_strength(rumble.high_frequency),
+                    duration_ms,
+                )?;
+
+                if rumble.left_trigger > 0 || rumble.right_trigger > 0 {
+                    gamepad.set_rumble_triggers(
+                        percent_to_strength(rumble.left_trigger),
+                        percent_to_strength(rumble.right_trigger),
+                        duration_ms,
+                    )?;
+                }
+            }
+            BackendCommand::SetLed { id, color } => {
+                let gamepad = self
+                    .gamepads
+                    .get_mut(id)
+                    .ok_or(format!("Gamepad {} is not open", id))?;
+
+                if gamepad.has_led() {
+                    gamepad.set_led(color.r, color.g, color.b)?;
+                }
+            }
+            BackendCommand::AddSdlMappings { mappings } => {
+                for mapping in mappings {
+                    if let Err(e) = self.controller_subsystem.add_mapping(mapping) {
+                        eprintln!("Invalid SDL mapping {}: {}", mapping, e);
+                    }
+                }
+            }
+            BackendCommand::SetRawCapture { id, enabled } => {
+                if *enabled {
+                    self.raw_capture.insert(*id);
+                } else {
+                    self.raw_capture.remove(id);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn sdl_button_to_gamepad_button(button: &Button) -> GamepadButton {
+    match button {
+        Button::South => GamepadButton::A,
+        Button::East => GamepadButton::B,
+        Button::West => GamepadButton::X,
+        Button::North => GamepadButton::Y,
+        Button::Back => GamepadButton::Back,
+        Button::Guide => GamepadButton::Guide,
+        Button::Start => GamepadButton::Start,
+        Button::LeftStick => GamepadButton::LeftStick,
+        Button::RightStick => GamepadButton::RightStick,
+        Button::LeftShoulder => GamepadButton::LeftShoulder,
+        Button::RightShoulder => GamepadButton::RightShoulder,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        Button::Misc1 => GamepadButton::Misc1,
+        Button::Misc2 => GamepadButton::Misc2,
+        Button::Misc3 => GamepadButton::Misc3,
+        Button::Misc4 => GamepadButton::Misc4,
+        Button::Misc5 => GamepadButton::Misc5,
+        Button::Misc6 => GamepadButton::Misc6,
+        // Numbered like the paddles of SDL mapping strings
+        Button::RightPaddle1 => GamepadButton::Paddle1,
+        Button::LeftPaddle1 => GamepadButton::Paddle2,
+        Button::RightPaddle2 => GamepadButton::Paddle3,
+        Button::LeftPaddle2 => GamepadButton::Paddle4,
+        Button::Touchpad => GamepadButton::Touchpad,
+    }
+}
+
+fn percent_to_strength(percent: u8) -> u16 {
+    (percent.min(100) as u32 * u16::MAX as u32 / 100) as u16
+}
+
+fn read_power_state(gamepad: &Gamepad) -> PowerState {
+    to_power_state(gamepad.connection_state(), gamepad.power_info())
+}
+
+fn read_joystick_power_state(joystick: &Joystick) -> PowerState {
+    to_power_state(joystick.connection_state(), joystick.power_info())
+}
+
+fn to_power_state(
+    connection_state: Result<ConnectionState, sdl3::Error>,
+    power_info: PowerInfo,
+) -> PowerState {
+    let connection = match connection_state {
+        Ok(ConnectionState::Wired) => ConnectionType::Wired,
+        Ok(ConnectionState::Wireless) => ConnectionType::Wireless,
+        _ => ConnectionType::Unknown,
+    };
+
+    let battery_status = match power_info.state {
+        PowerLevel::OnBattery => BatteryStatus::OnBattery,
+        PowerLevel::Charging => BatteryStatus::Charging,
+        PowerLevel::Charged => BatteryStatus::Charged,
+        PowerLevel::NoBattery => BatteryStatus::NoBattery,
+        _ => BatteryStatus::Unknown,
+    };
+
+    // SDL reports -1 when the percentage is unknown
+    let battery_percent = u8::try_from(power_info.percentage)
+        .ok()
+        .map(|percent| percent.min(100));
+
+    PowerState {
+        connection,
+        battery_status,
+        battery_percent,
+    }
+}
+
+fn enable_motion_sensors(gamepad: &Gamepad, state: &GamepadState) {
+    let sensors = [
+        (SensorType::Gyroscope, state.motion().has_gyro),
+        (SensorType::Accelerometer, state.motion().has_accel),
+    ];
+
+    for (sensor, supported) in sensors {
+        if !supported {
+            continue;
+        }
+
+        if let Err(e) = gamepad.sensor_set_enabled(sensor, true) {
+            eprintln!("Unable to enable {:?} on {}: {}", sensor, state.id(), e);
+        }
+    }
+}
+
+pub fn sdl_sensor_to_motion_sensor(sensor: &SensorType) -> Option<MotionSensor> {
+    match sensor {
+        SensorType::Gyroscope => Some(MotionSensor::Gyroscope),
+        SensorType::Accelerometer => Some(MotionSensor::Accelerometer),
+        _ => None,
+    }
+}
+
+pub fn sdl_hat_to_hat_position(state: &HatState) -> HatPosition {
+    match state {
+        HatState::Centered => HatPosition::Centered,
+        HatState::Up => HatPosition::Up,
+        HatState::Right => HatPosition::Right,
+        HatState::Down => HatPosition::Down,
+        HatState::Left => HatPosition::Left,
+        HatState::RightUp => HatPosition::RightUp,
+        HatState::RightDown => HatPosition::RightDown,
+        HatState::LeftUp => HatPosition::LeftUp,
+        HatState::LeftDown => HatPosition::LeftDown,
+    }
+}
+
+pub fn sdl_axis_to_gamepad_axis(axis: &Axis) -> GamepadAxis {
+    match axis {
+        Axis::LeftX => GamepadAxis::LeftX,
+        Axis::LeftY => GamepadAxis::LeftY,
+        Axis::RightX => GamepadAxis::RightX,
+        Axis::RightY => GamepadAxis::RightY,
+        Axis::TriggerLeft => GamepadAxis::TriggerLeft,
+        Axis::TriggerRight => GamepadAxis::TriggerRight,
+    }
+}
@@ -11,7 +11,7 @@ export function ButtonMouseMoveActionBuilder({ className, gamepad, action: initi
     const isEdit = useConstant<boolean>(() => !!initialAction || !!initialConditions?.length);
 
     const [direction, setDirection] = useState<Direction>(initialAction?.direction ?? "up");
-    const [speed, setSpeed] = useState<number>(initialAction?.speed ?? 10);
+    const [speed, setSpeed] = useState<number>(initialAction?.speed ?? 1000);
     const [conditions, setConditions] = useState<ConditionType[]>(initialConditions ?? []);
 
     const hasChanged = useMemo(() => {
@@ -24,12 +24,12 @@ export function ButtonMouseMoveActionBuilder({ className, gamepad, action: initi
 
     const handleCancelEdit = useCallback(() => {
         setDirection(() => initialAction?.direction ?? "up");
-        setSpeed(() => initialAction?.speed ?? 10);
+        setSpeed(() => initialAction?.speed ?? 1000);
         setConditions(() => initialConditions ?? []);
     }, [initialAction, initialConditions]);
 
     const handleSpeedChange = useCallback((e: React.ChangeEvent<HTMLInputElement>) => {
-        const value = Math.max(1, Math.min(100, Number(e.target.value))) || 10; // clamp the value between 1 and 100 and prevent NaN
+        const value = Math.max(1, Math.min(5000, Number(e.target.value))) || 1000; // clamp the value between 1 and 5000 and prevent NaN
         console.log(value);
         setSpeed(() => value);
     }, []);
@@ -37,8 +37,8 @@ export function ButtonMouseMoveActionBuilder({ className, gamepad, action: initi
     return (
         <ActionWrapper className={cn(className)} title="Move cursor" gamepad={gamepad} conditions={conditions} isEdit={isEdit} hasChanged={hasChanged} onCancelEdit={handleCancelEdit} onDelete={() => onDelete?.()} onSave={saveMapping} onConditionChange={setConditions}>
             <div className="flex flex-row gap-2 items-center mb-1.5 overflow-hidden">
-                <label htmlFor="speed">Speed</label>
-                <input className="grow bg-gray-300 rounded-md p-1" type="number" min={1} max={100} value={speed} step={1} onChange={handleSpeedChange} />
+                <label htmlFor="speed">Speed (px/s)</label>
+                <input className="grow bg-gray-300 rounded-md p-1" type="number" min={1} max={5000} value={speed} step={1} onChange={handleSpeedChange} />
             </div>
             <div className="flex flex-row gap-1 items-center mb-1.5">
                 <label htmlFor="direction">Direction</label>
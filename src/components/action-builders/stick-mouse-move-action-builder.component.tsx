@@ -12,7 +12,7 @@ export function StickMouseMoveActionBuilder({ className, gamepad, action: initia
     const isEdit = useConstant<boolean>(() => !!initialAction || !!initialConditions?.length);
 
     const [mode, setMode] = useState<MouseMoveMode>(initialAction?.mode ?? "relative");
-    const [speed, setSpeed] = useState<number>(initialAction?.speed ?? 10);
+    const [speed, setSpeed] = useState<number>(initialAction?.speed ?? 1000);
     const [conditions, setConditions] = useState<ConditionType[]>(initialConditions ?? []);
 
     const hasChanged = useMemo(() => {
@@ -25,12 +25,12 @@ export function StickMouseMoveActionBuilder({ className, gamepad, action: initia
 
     const handleCancelEdit = useCallback(() => {
         setMode(() => initialAction?.mode ?? "relative");
-        setSpeed(() => initialAction?.speed ?? 10);
+        setSpeed(() => initialAction?.speed ?? 1000);
         setConditions(() => initialConditions ?? []);
     }, [initialAction, initialConditions]);
 
     const handleSpeedChange = useCallback((e: React.ChangeEvent<HTMLInputElement>) => {
-        const value = Math.max(1, Math.min(100, Number(e.target.value))) || 10; // clamp the value between 1 and 100 and prevent NaN
+        const value = Math.max(1, Math.min(5000, Number(e.target.value))) || 1000; // clamp the value between 1 and 5000 and prevent NaN
         setSpeed(() => value);
     }, []);
 
@@ -39,8 +39,8 @@ export function StickMouseMoveActionBuilder({ className, gamepad, action: initia
             <AnimatePresence>
                 {mode === "relative" && (
                     <motion.div className="flex flex-row gap-2 items-center mb-1.5 overflow-hidden" initial={{ height: 0 }} animate={{ height: "auto" }} exit={{ height: 0 }}>
-                        <label htmlFor="speed">Speed</label>
-                        <input className="grow bg-gray-300 rounded-md p-1" type="number" min={1} max={100} value={speed} step={1} onChange={handleSpeedChange} />
+                        <label htmlFor="speed">Speed (px/s)</label>
+                        <input className="grow bg-gray-300 rounded-md p-1" type="number" min={1} max={5000} value={speed} step={1} onChange={handleSpeedChange} />
                     </motion.div>
                 )}
             </AnimatePresence>
@@ -33,7 +33,7 @@ export function StickMouseScrollActionBuilder({ className, gamepad, action: init
     return (
         <ActionWrapper className={className} title="Scroll" gamepad={gamepad} conditions={conditions} isEdit={isEdit} hasChanged={hasChanged} onCancelEdit={handleCancelEdit} onDelete={() => onDelete?.()} onSave={saveMapping} onConditionChange={setConditions}>
             <div className="flex flex-row gap-2 items-center mb-1.5 overflow-hidden">
-                <label htmlFor="speed">Speed</label>
+                <label htmlFor="speed">Speed (steps/s)</label>
                 <input className="grow bg-gray-300 rounded-md p-1" type="number" min={1} max={100} value={speed} step={1} onChange={handleSpeedChange} />
             </div>
         </ActionWrapper>
@@ -0,0 +1,28 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { GamepadAxis } from "./gamepad";
+
+/**
+ * Raw range of one axis, the calibrated value is 0 at `center` and full scale at `min` and `max`.
+ */
+export type AxisCalibration = { center: number, min: number, max: number, };
+
+export type CalibrationStep = "center" | "range";
+
+/**
+ * Progress of the calibration wizard, streamed to the frontend.
+ */
+export type CalibrationUpdate = { "type": "prompt", step: CalibrationStep, seconds: number, } | { "type": "done", calibration: ControllerCalibration, leftStickError: number | null, rightStickError: number | null, };
+
+/**
+ * Calibration of a controller, stored on its known device and applied before any mapping sees the values.
+ */
+export type ControllerCalibration = { axes: Partial<Record<GamepadAxis, AxisCalibration>>, leftStick: StickCalibration | null, rightStick: StickCalibration | null, };
+
+/**
+ * Distance reached at full tilt in each direction once the axes are calibrated, 1.0 everywhere is a round stick.
+ */
+export type StickCalibration = { 
+/**
+ * One entry per direction starting from the right, `STICK_REACH_SECTORS` entries
+ */
+reach: Array<number>, };
@@ -1,4 +1,52 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
-import type { Mapping } from "./mapping";
+import type { ControllerCalibration } from "./calibration";
+import type { ControllerIdentity, GamepadButton, LedColor } from "./gamepad";
+import type { Mapping, StickDeadzones } from "./mapping";
 
-export type Config = { mappingActiveOnBoot: boolean, mappings: Array<Mapping>, deadzone: number, keyboardLayout: string | null, };
+export type Config = { 
+/**
+ * Missing from the configs stored before versioning, read as 0
+ */
+version: number, mappingActiveOnBoot: boolean, mappings: Array<Mapping>, deadzone: number, keyboardLayout: string | null, knownDevices: Array<KnownDevice>, lowBatteryThreshold: number, ledColors: LedColors, 
+/**
+ * SDL mapping lines (gamecontrollerdb format) added on top of the SDL database
+ */
+customSdlMappings: Array<string>, stickDeadzones: StickDeadzones, 
+/**
+ * Layers that can be stacked over `mappings`
+ */
+layers: Array<MappingLayer>, 
+/**
+ * Saved profiles, `mappings` and `layers` are the working copy of the active one
+ */
+profiles: Array<Profile>, activeProfile: string | null, };
+
+/**
+ * Controller seen at least once, recognized through its identity key when it reconnects.
+ */
+export type KnownDevice = { key: string, alias: string | null, identity: ControllerIdentity, ledColor: LedColor | null, calibration: ControllerCalibration | null, };
+
+export type LayerMode = "hold" | "toggle" | "oneShot";
+
+/**
+ * Lightbar colours reflecting the mapping state, the active profile's and the controller's own colours win over `active`.
+ */
+export type LedColors = { active: LedColor | null, paused: LedColor | null, };
+
+/**
+ * Mappings stacked over the base mappings while active, `button` only switches the layer and no longer fires its own mappings.
+ */
+export type MappingLayer = { id: string, name: string, button: GamepadButton, mode: LayerMode, mappings: Array<Mapping>, };
+
+/**
+ * Named set of mappings and layers, switched as a whole.
+ */
+export type Profile = { id: string, name: string, mappings: Array<Mapping>, layers: Array<MappingLayer>, 
+/**
+ * Lightbar colour while the profile is active, wins over the controller's own colour
+ */
+ledColor: LedColor | null, };
+
+export type ProfileList = { profiles: Array<ProfileSummary>, active: string, };
+
+export type ProfileSummary = { id: string, name: string, ledColor: LedColor | null, };
@@ -1,7 +1,66 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type GamepadAxis = "leftX" | "leftY" | "rightX" | "rightY" | "triggerLeft" | "triggerRight";
+export type BatteryStatus = "onBattery" | "charging" | "charged" | "noBattery" | "unknown";
 
-export type GamepadButton = "a" | "b" | "x" | "y" | "back" | "guide" | "start" | "leftStick" | "rightStick" | "leftShoulder" | "rightShoulder" | "dPadUp" | "dPadDown" | "dPadLeft" | "dPadRight" | "misc1" | "paddle1" | "paddle2" | "paddle3" | "paddle4" | "touchpad";
+export type ConnectionType = "wired" | "wireless" | "unknown";
 
-export type GamepadState = { id: number, name: string, buttons: Record<GamepadButton, boolean>, axis: Record<GamepadAxis, number>, };
+/**
+ * Identifies a physical controller across reconnects, unlike the SDL instance id.
+ */
+export type ControllerIdentity = { guid: string, vendorId: number, productId: number, serial: string | null, controllerType: ControllerType, };
+
+export type ControllerType = "xbox360" | "xboxOne" | "ps3" | "ps4" | "ps5" | "switchPro" | "switchJoyCon" | "unknown";
+
+/**
+ * Gamepads have a standard layout, joysticks (flight sticks, pedals, button boxes) only expose numbered inputs.
+ */
+export type DeviceKind = "gamepad" | "joystick";
+
+/**
+ * Serialized as a camelCase name, raw joystick axes are serialized as `joystickAxis<index>`.
+ */
+export type GamepadAxis = "leftX" | "leftY" | "rightX" | "rightY" | "triggerLeft" | "triggerRight" | `joystickAxis${number}`;
+
+/**
+ * Serialized as a camelCase name, buttons SDL doesn't name yet are serialized as `unknown<index>`
+ * and raw joystick buttons as `joystickButton<index>`, so they can still be used as map keys and targeted by mappings.
+ */
+export type GamepadButton = "a" | "b" | "x" | "y" | "back" | "guide" | "start" | "leftStick" | "rightStick" | "leftShoulder" | "rightShoulder" | "dPadUp" | "dPadDown" | "dPadLeft" | "dPadRight" | "misc1" | "misc2" | "misc3" | "misc4" | "misc5" | "misc6" | "paddle1" | "paddle2" | "paddle3" | "paddle4" | "touchpad" | `unknown${number}` | `joystickButton${number}`;
+
+export type GamepadState = { id: number, name: string, buttons: Record<GamepadButton, boolean>, axis: Record<GamepadAxis, number>, identity: ControllerIdentity, playerSlot: number, power: PowerState, motion: MotionState, touchpadFingers: Array<TouchpadFinger>, kind: DeviceKind, hats: Array<HatPosition>, };
+
+/**
+ * Position of a joystick hat switch (d-pad on devices without a gamepad mapping).
+ */
+export type HatPosition = "centered" | "up" | "right" | "down" | "left" | "rightUp" | "rightDown" | "leftUp" | "leftDown";
+
+/**
+ * Single input change, published in order next to the gamepads snapshot so quick taps are never coalesced.
+ */
+export type InputEvent = { gamepadId: number, 
+/**
+ * Microseconds since the app started
+ */
+timestamp: number, kind: InputEventKind, };
+
+export type InputEventKind = { "type": "buttonDown", button: GamepadButton, } | { "type": "buttonUp", button: GamepadButton, } | { "type": "axisChanged", axis: GamepadAxis, value: number, };
+
+export type LedColor = { r: number, g: number, b: number, };
+
+export type LowBatteryWarning = { gamepadId: number, name: string, batteryPercent: number, };
+
+export type MotionSensor = "gyroscope" | "accelerometer";
+
+export type MotionState = { hasGyro: boolean, hasAccel: boolean, gyro: SensorReading | null, accel: SensorReading | null, };
+
+export type PowerState = { connection: ConnectionType, batteryStatus: BatteryStatus, batteryPercent: number | null, };
+
+/**
+ * Gyroscope readings are in radians per second, accelerometer readings in m/s².
+ */
+export type SensorReading = { x: number, y: number, z: number, timestamp: number, };
+
+/**
+ * Finger on the touchpad, positions are normalized between 0 and 1 from the top-left corner.
+ */
+export type TouchpadFinger = { finger: number, x: number, y: number, pressure: number, };
@@ -1,26 +1,111 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
-import type { GamepadAxis, GamepadButton } from "./gamepad";
+import type { GamepadAxis, GamepadButton, LedColor } from "./gamepad";
 
-export type Action = { "type": "pressKeys", keys: Array<string>, } | { "type": "writeText", text: string, } | { "type": "mouseMoveDirection", direction: Direction, speed: number, } | { "type": "mouseClick", button: MouseButton, } | { "type": "mouseMoveStick", mode: MouseMoveMode, speed: number, } | { "type": "scrollDirection", direction: Direction, speed: number, } | { "type": "scrollStick", speed: number, } | { "type": "toogleMappingActive" } | { "type": "toogleVirtualKeyboard" } | { "type": "openWebsite", url: string, } | { "type": "openFile", path: string, };
+export type Action = { "type": "pressKeys", keys: Array<string>, } | { "type": "writeText", text: string, } | { "type": "mouseMoveDirection", direction: Direction, speed: number, } | { "type": "mouseClick", button: MouseButton, } | { "type": "mouseMoveStick", mode: MouseMoveMode, speed: number, } | { "type": "mouseMoveGyro", settings: GyroMouseSettings, } | { "type": "scrollDirection", direction: Direction, speed: number, } | { "type": "scrollStick", speed: number, } | { "type": "rumble", rumble: Rumble, } | { "type": "setLedColor", color: LedColor, } | { "type": "toogleMappingActive" } | { "type": "toogleVirtualKeyboard" } | { "type": "switchProfile", profile: string, } | { "type": "nextProfile" } | { "type": "previousProfile" } | { "type": "openWebsite", url: string, } | { "type": "openFile", path: string, } | { "type": "macro", sequence: MacroSequence, };
 
-export type AxisStickMapping = { id: string, stick: StickType, action: Action, conditions: Array<ConditionType>, };
+export type AxisStickMapping = { id: string, stick: StickType, action: Action, conditions: Array<ConditionType>, target?: ControllerTarget | null, curve?: ResponseCurve | null, };
 
-export type AxisTriggerMapping = { id: string, axis: GamepadAxis, threshold: number, conditions: Array<ConditionType>, action: Action, };
+export type AxisTriggerMapping = { id: string, axis: GamepadAxis, threshold: number, conditions: Array<ConditionType>, target?: ControllerTarget | null, action: Action, feedback?: Rumble | null, curve?: ResponseCurve | null, };
 
 export type BooleanOperator = "and" | "or";
 
-export type ButtonMapping = { id: string, button: GamepadButton, action: Action, conditions: Array<ConditionType>, };
+export type ButtonMapping = { id: string, button: GamepadButton, action: Action, conditions: Array<ConditionType>, target?: ControllerTarget | null, feedback?: Rumble | null, trigger?: ButtonTrigger | null, 
+/**
+ * Only used with the `Press` trigger
+ */
+turbo?: TurboSettings | null, };
+
+/**
+ * When a button mapping fires, durations are in milliseconds.
+ * A button can carry one mapping per trigger, e.g. tap B for Escape and hold B for Alt+F4.
+ */
+export type ButtonTrigger = { "type": "press" } | { "type": "tap", ms: number, } | { "type": "hold", ms: number, } | { "type": "doubleTap", ms: number, } | { "type": "release" };
 
 export type Condition = { "type": "buttonPressed", button: GamepadButton, } | { "type": "buttonNotPressed", button: GamepadButton, };
 
 export type ConditionType = { "operator": "and" } & Condition | { "operator": "or" } & Condition;
 
+/**
+ * Restricts a mapping to one controller, either by its identity key or by its player slot (starting at 1).
+ */
+export type ControllerTarget = { "type": "device", key: string, } | { "type": "playerSlot", slot: number, };
+
+export type CurvePoint = { x: number, y: number, };
+
+/**
+ * Values are fractions of the full stick range.
+ * `outer` is the magnitude from which the output is saturated, and `anti` the smallest output
+ * once out of the deadzone, to compensate for the deadzone of a game.
+ */
+export type DeadzoneSettings = { shape: DeadzoneShape, inner: number, outer: number, anti: number, };
+
+/**
+ * `Axial` handles each axis on its own (square deadzone), `Radial` cuts a circle but keeps the raw
+ * magnitude outside of it, `ScaledRadial` cuts a circle and rescales the magnitude so it starts from zero.
+ */
+export type DeadzoneShape = "axial" | "radial" | "scaledRadial";
+
 export type Direction = "up" | "down" | "left" | "right";
 
-export type Mapping = { "type": "buttonPressed" } & ButtonMapping | { "type": "axisTrigger" } & AxisTriggerMapping | { "type": "axisStick" } & AxisStickMapping;
+export type GyroAxis = "yaw" | "roll";
+
+/**
+ * Sensitivity is in pixels per degree of rotation, deadzone in degrees per second.
+ */
+export type GyroMouseSettings = { sensitivityX: number, sensitivityY: number, horizontalAxis: GyroAxis, deadzone: number, ratchet: GyroRatchet | null, };
+
+export type GyroRatchet = { button: GamepadButton, mode: RatchetMode, };
+
+/**
+ * Ordered steps run in the background, so a long macro doesn't hold the other mappings back.
+ * With `cancel_on_release` the macro stops when the input is released, otherwise it runs to completion.
+ */
+export type MacroSequence = { steps: Array<MacroStep>, cancelOnRelease: boolean, };
+
+/**
+ * Keys left down by a macro are released when it ends. `Action` runs the action once,
+ * continuous ones (mouse movement, scrolling) are ignored.
+ */
+export type MacroStep = { "type": "keyDown", key: string, } | { "type": "keyUp", key: string, } | { "type": "text", text: string, } | { "type": "mouseClick", button: MouseButton, } | { "type": "mouseMove", x: number, y: number, } | { "type": "scroll", x: number, y: number, } | { "type": "wait", ms: number, } | { "type": "action", action: Action, };
+
+export type Mapping = { "type": "buttonPressed" } & ButtonMapping | { "type": "axisTrigger" } & AxisTriggerMapping | { "type": "axisStick" } & AxisStickMapping | { "type": "touchpad" } & TouchpadMapping;
 
 export type MouseButton = "left" | "right" | "middle";
 
 export type MouseMoveMode = "relative" | "absolute";
 
-export type StickType = "leftStick" | "rightStick";
+export type RatchetMode = "hold" | "toggle";
+
+/**
+ * Reshapes an analog magnitude between 0 and 1, applied after the deadzone.
+ * `Power` raises it to `exponent` (above 1 gives more precision near the center),
+ * `SCurve` blends towards a smoothstep by `strength` (0 to 1),
+ * and `Custom` interpolates linearly between its points, sorted by `x` when read.
+ */
+export type ResponseCurve = { "type": "linear" } | { "type": "power", exponent: number, } | { "type": "sCurve", strength: number, } | { "type": "custom", points: Array<CurvePoint>, };
+
+/**
+ * Motor and trigger strengths are percentages, the effect stops after `duration_ms`.
+ */
+export type Rumble = { lowFrequency: number, highFrequency: number, leftTrigger: number, rightTrigger: number, durationMs: number, };
+
+/**
+ * Deadzone of each stick, `None` uses the global `deadzone` with the default shape.
+ */
+export type StickDeadzones = { leftStick: DeadzoneSettings | null, rightStick: DeadzoneSettings | null, };
+
+export type StickType = "leftStick" | "rightStick" | "gyro";
+
+/**
+ * Uses the touchpad like a laptop trackpad.
+ * `sensitivity` is the cursor travel across the full pad width in tens of pixels,
+ * `scroll_speed` the number of scroll steps across the full pad height with two fingers,
+ * and `right_click_zone` the percentage of the pad width from which clicks become right clicks.
+ */
+export type TouchpadMapping = { id: string, sensitivity: number, scrollSpeed: number, tapToClick: boolean, rightClickZone: number | null, conditions: Array<ConditionType>, target?: ControllerTarget | null, };
+
+/**
+ * Repeats a press-triggered action `rate` times per second, the output stays down for `duty_cycle` percent of each period.
+ * With `toggle`, a press latches the turbo on and the next press turns it off.
+ */
+export type TurboSettings = { rate: number, dutyCycle: number, toggle: boolean, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HatPosition } from "./gamepad";
+
+/**
+ * Progress of the "map this controller" wizard, streamed to the frontend.
+ */
+export type MappingWizardUpdate = { "type": "prompt", element: string, step: number, total: number, } | { "type": "recorded", element: string, binding: string | null, } | { "type": "done", mapping: string, };
+
+/**
+ * Input read straight from the joystick, before SDL applies a gamepad mapping.
+ */
+export type RawInput = { "type": "button", index: number, pressed: boolean, } | { "type": "axis", index: number, value: number, } | { "type": "hat", index: number, position: HatPosition, };
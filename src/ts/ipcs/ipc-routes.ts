@@ -1,11 +1,20 @@
 import { ConfigKey, ConfigType } from "../../services/config.service";
-import { Config } from "../bindings/config";
-import { GamepadState } from "../bindings/gamepad";
+import { CalibrationUpdate } from "../bindings/calibration";
+import { Config, ProfileList } from "../bindings/config";
+import { GamepadState, InputEvent, LedColor, LowBatteryWarning } from "../bindings/gamepad";
+import { MappingWizardUpdate } from "../bindings/sdl-mapping";
 
 export interface IpcChannelMapping {
 		"controllers-states": { request: void; response: GamepadState[] }
         "get-config": { request: void; response: Config }
         "set-config": { request: { key: ConfigKey; value: ConfigType<ConfigKey> }; response: void }
+        "get-profiles": { request: void; response: ProfileList }
+        "create-profile": { request: string; response: string }
+        "clone-profile": { request: { id: string; name: string }; response: string }
+        "rename-profile": { request: { id: string; name: string }; response: void }
+        "set-profile-led": { request: { id: string; color: LedColor | null }; response: void }
+        "delete-profile": { request: string; response: void }
+        "activate-profile": { request: string; response: void }
         "open-file": { request: void; response: string | null }
         "toogle-mapping-active": { request: void; response: void }
         "is-mapping-active": { request: void; response: boolean }
@@ -14,6 +23,16 @@ export interface IpcChannelMapping {
         "release-keys": { request: string[]; response: void }
         "write-text": { request: string; response: void }
         "on-vk-key-pressed": { request: void; response: string }
+        "input-events": { request: void; response: InputEvent }
+        "on-low-battery": { request: void; response: LowBatteryWarning }
+        "rename-device": { request: { key: string; alias: string | null }; response: void }
+        "forget-device": { request: string; response: void }
+        "set-device-led": { request: { key: string; color: LedColor | null }; response: void }
+        "import-sdl-mappings": { request: string; response: number }
+        "remove-sdl-mapping": { request: string; response: void }
+        "calibrate-controller": { request: number; response: CalibrationUpdate }
+        "reset-calibration": { request: string; response: void }
+        "map-controller": { request: number; response: MappingWizardUpdate }
 }
 
 export type IpcRequestType<Channel extends keyof IpcChannelMapping> = IpcChannelMapping[Channel]["request"];